@@ -1,10 +1,8 @@
-use anyhow::Result;
-use bincode;
+use anyhow::{anyhow, Result};
 use indicatif::{ProgressBar, ProgressStyle};
-use pokepalette::sprite::Sprite;
+use pokepalette::sprite::{Form, Gender, Sprite};
 use pokepalette::DB_FILE_NAME;
 use pokepalette::KRABBY_BASE_URL;
-use reqwest;
 use serde_json::Value;
 use std::env;
 use std::path::PathBuf;
@@ -13,49 +11,97 @@ use tokio::time::{sleep, Duration};
 // Create DB in root dir
 const PROJECT_ROOT: &str = env!("CARGO_MANIFEST_DIR");
 
+// Forms that must make it into the database, failed downloads would
+// otherwise leave them out silently
+const REQUIRED_FORMS: [(&str, &str); 7] = [
+    ("rotom", "heat"),
+    ("rotom", "wash"),
+    ("rotom", "frost"),
+    ("rotom", "fan"),
+    ("rotom", "mow"),
+    ("urshifu", "rapid-strike"),
+    ("giratina", "origin"),
+];
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let mut sprites: Vec<Sprite> = Vec::new();
 
     let pokemon_names = get_pokemon_list().await?;
-    let total_downloads = pokemon_names.len() * 2; // 2x because of shinies
-
-    println!("Downloading {} sprites from krabby", total_downloads);
-
-    // Create progress bar
-    let bar = ProgressBar::new(total_downloads as u64);
-    bar.set_style(ProgressStyle::with_template(
-        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
-    )?);
 
     // Create all urls
     let mut all_urls = Vec::new();
-    for (name, gen) in pokemon_names {
+    for (name, form, gen) in pokemon_names {
+        let file_name = match &form {
+            Some(form) => format!("{}-{}", name, form),
+            None => name.clone(),
+        };
         for variant in ["regular", "shiny"] {
             let url = format!(
                 "{}/assets/colorscripts/{}/{}",
-                KRABBY_BASE_URL, variant, name
+                KRABBY_BASE_URL, variant, file_name
             );
-            all_urls.push((url, name.clone(), gen, variant == "shiny"));
+            all_urls.push((
+                url,
+                name.clone(),
+                form.clone(),
+                gen,
+                variant == "shiny",
+                Gender::Regular,
+            ));
+
+            // Female differences only exist for base forms, most downloads will 404
+            if form.is_none() {
+                let url = format!(
+                    "{}/assets/colorscripts/{}/female/{}",
+                    KRABBY_BASE_URL, variant, file_name
+                );
+                all_urls.push((
+                    url,
+                    name.clone(),
+                    None,
+                    gen,
+                    variant == "shiny",
+                    Gender::Female,
+                ));
+            }
         }
     }
 
+    let total_downloads = all_urls.len();
+    println!("Downloading {} sprites from krabby", total_downloads);
+
+    // Create progress bar
+    let bar = ProgressBar::new(total_downloads as u64);
+    bar.set_style(ProgressStyle::with_template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+    )?);
+
     // Process in batchces
     let batch_size = 10;
     for batch in all_urls.chunks(batch_size) {
         let mut tasks = Vec::new();
 
-        for (url, name, gen, is_shiny) in batch {
+        for (url, name, form, gen, is_shiny, gender) in batch {
             let url = url.clone();
             let name_clone = name.clone();
+            let form_clone = form.clone();
             let is_shiny = *is_shiny;
             let gen = *gen;
+            let gender = *gender;
 
             let task = tokio::spawn(async move {
                 sleep(Duration::from_millis(100)).await;
 
                 match download_file(&url).await {
-                    Ok(content) => Sprite::from_content(&content, &name_clone, gen, is_shiny),
+                    Ok(content) => Sprite::from_content(
+                        &content,
+                        &name_clone,
+                        form_clone.as_deref(),
+                        gen,
+                        is_shiny,
+                        gender,
+                    ),
                     Err(_) => Err(anyhow::anyhow!("Download failed")),
                 }
             });
@@ -79,6 +125,7 @@ async fn main() -> Result<()> {
     println!("Created {} sprites", sprites.len());
 
     sprites.sort_by(|a, b| a.name.cmp(&b.name));
+    check_variants(&sprites)?;
 
     println!("Creating bin");
 
@@ -91,7 +138,31 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn get_pokemon_list() -> Result<Vec<(String, u8)>> {
+fn check_variants(sprites: &[Sprite]) -> Result<()> {
+    for (name, form) in REQUIRED_FORMS {
+        let form = Form::from(form);
+        if !sprites
+            .iter()
+            .any(|sprite| sprite.name == name && sprite.form == form)
+        {
+            return Err(anyhow!(
+                "Missing sprite {} -f {}, not writing the database",
+                name,
+                form
+            ));
+        }
+    }
+
+    if !sprites.iter().any(|sprite| sprite.gender == Gender::Female) {
+        return Err(anyhow!(
+            "No female sprites downloaded, not writing the database"
+        ));
+    }
+
+    Ok(())
+}
+
+async fn get_pokemon_list() -> Result<Vec<(String, Option<String>, u8)>> {
     let list_url = format!("{}/assets/pokemon.json", KRABBY_BASE_URL);
     let response = download_file(&list_url).await?;
     let json: Value = serde_json::from_str(&response)?;
@@ -105,12 +176,13 @@ async fn get_pokemon_list() -> Result<Vec<(String, u8)>> {
 }
 
 async fn download_file(url: &str) -> Result<String> {
-    let response = reqwest::get(url).await?;
+    let response = reqwest::get(url).await?.error_for_status()?;
     let text = response.text().await?;
     Ok(text)
 }
 
-fn get_variants(pokemon: &Value) -> Vec<(String, u8)> {
+// Forms are kept apart from the slug so sprites keep krabby's `-f <form>` naming
+fn get_variants(pokemon: &Value) -> Vec<(String, Option<String>, u8)> {
     let Some(slug) = pokemon["slug"].as_str() else {
         return vec![];
     };
@@ -119,14 +191,14 @@ fn get_variants(pokemon: &Value) -> Vec<(String, u8)> {
         return vec![];
     };
 
-    let mut variants = vec![(slug.to_string(), gen as u8)];
+    let mut variants = vec![(slug.to_string(), None, gen as u8)];
 
     if let Some(forms) = pokemon["forms"].as_array() {
         variants.extend(
             forms
                 .iter()
                 .filter_map(|form| form.as_str())
-                .map(|form| (format!("{}-{}", slug, form), gen as u8)),
+                .map(|form| (slug.to_string(), Some(form.to_string()), gen as u8)),
         );
     }
 
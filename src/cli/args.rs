@@ -1,31 +1,321 @@
-use crate::DEFAULT_TOP_K;
-use clap::{ArgGroup, Parser};
+use crate::cli::cache::PaletteCache;
+use crate::cli::output::OutputFormat;
+use crate::colorquant::accent::{with_accent_weights, with_split_accents};
+use crate::colorquant::cvd::with_cvd;
+use crate::colorquant::{
+    open_image, palette_distance_in, AccentCurve, Background, Crop, Cvd, Deficiency, Focus, Frames,
+    Harmony, MatchMode, Region, RegionPreset, Space,
+};
+use crate::matcher::{Match, Metric};
+use crate::pokemondatabase::{parse_generation, PokemonDatabase, SpriteFilter};
+use crate::ranking::{pick_weighted, Distinct, Pick};
+use crate::{PaletteMatcher, PaletteMatcherBuilder, DEFAULT_TOP_K};
+use anyhow::Result;
+use clap::{ArgGroup, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 /// Find pokemon color palettes that are similar to your image
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None,
-    group(ArgGroup::new("shiny-filter")
-        .args(["no_shiny", "all_shiny"])
-        .multiple(false)),
-    group(ArgGroup::new("mega-filter")
-        .args(["no_mega", "all_mega"])
-        .multiple(false)),
-    group(ArgGroup::new("gmax-filter")
-        .args(["no_gmax", "all_gmax"])
-        .multiple(false)),
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
 )]
 pub struct Args {
-    /// Path of the image
-    pub image: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    /// Number of pokemon returned
+    /// Paths of images, directories or glob patterns, `-` reads stdin
+    #[arg(required = true)]
+    pub images: Vec<String>,
+
+    /// Search directories recursively
+    #[arg(short, long, default_value_t = false)]
+    pub recursive: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    #[command(flatten)]
+    pub matching: MatchArgs,
+
+    /// Print additional information
+    #[arg(short, long, default_value_t = false)]
+    pub verbose: bool,
+
+    /// Show which colors were paired for each match and what they add to the
+    /// distance. Only explains the plain CIELAB distance, as text.
+    #[arg(long, default_value_t = false, conflicts_with_all = [
+        "format", "match_mode", "space", "accent", "accent_curve", "split_accents", "cvd",
+        "harmony", "background",
+    ])]
+    pub explain: bool,
+
+    /// Config file to read instead of $XDG_CONFIG_HOME/pokepalette/config.toml
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Apply the settings of a [preset.<name>] section of the config
+    #[arg(long, global = true)]
+    pub preset: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rank images by how well they match a pokemon
+    WallpaperFor(WallpaperForArgs),
+
+    /// Find pokemon whose palettes are similar to a given pokemon
+    Similar(SimilarArgs),
+
+    /// Match an image again whenever it changes, e.g. the current wallpaper
+    Watch(WatchArgs),
+
+    /// Inspect the config file
+    Config(ConfigArgs),
+
+    /// Manage the cache of image palettes
+    Cache(CacheArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct WatchArgs {
+    /// Image, symlink to an image or file holding the path of an image (like
+    /// pywal's `~/.cache/wal/wal`)
+    pub path: PathBuf,
+
+    /// Write the best match to a file instead of printing it
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Run a command with the best match, `{name}`, `{flags}` and `{image}`
+    /// are replaced, e.g. 'krabby name {name} {flags}'
+    #[arg(short, long)]
+    pub exec: Option<String>,
+
+    /// Milliseconds to wait for changes to settle before matching
+    #[arg(long, default_value_t = 500)]
+    pub debounce: u64,
+
+    #[command(flatten)]
+    pub matching: MatchArgs,
+}
+
+/// Arguments of the commands that match images against the database
+#[derive(clap::Args, Debug)]
+pub struct MatchArgs {
+    #[command(flatten)]
+    pub image: ImageArgs,
+
+    #[command(flatten)]
+    pub ranking: RankingArgs,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Only keep sprites of the given form (e.g. regular, mega-x, origin, female)
+    #[arg(long)]
+    pub form: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective settings, with config and preset applied. Takes no
+    /// other flags, command line values are not part of the config.
+    Show,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Remove every cached image palette
+    Clear,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct WallpaperForArgs {
+    /// Name of the pokemon
+    pub name: String,
+
+    /// Paths of images, directories or glob patterns, `-` reads stdin
+    #[arg(required = true)]
+    pub images: Vec<String>,
+
+    /// Use the shiny variant
+    #[arg(short, long, default_value_t = false)]
+    pub shiny: bool,
+
+    /// Form of the pokemon, as passed to krabby
+    #[arg(short, long)]
+    pub form: Option<String>,
+
+    /// Search directories recursively
+    #[arg(short, long, default_value_t = false)]
+    pub recursive: bool,
+
+    #[command(flatten)]
+    pub image: ImageArgs,
+
+    /// Number of images returned
     #[arg(short, long, default_value_t = DEFAULT_TOP_K)]
     pub top_k: usize,
 
     /// Print additional information
     #[arg(short, long, default_value_t = false)]
     pub verbose: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SimilarArgs {
+    /// Name of the pokemon
+    pub name: String,
 
+    /// Use the shiny variant
+    #[arg(short, long, default_value_t = false)]
+    pub shiny: bool,
+
+    /// Form of the pokemon, as passed to krabby
+    #[arg(short, long)]
+    pub form: Option<String>,
+
+    /// Print additional information
+    #[arg(short, long, default_value_t = false)]
+    pub verbose: bool,
+
+    #[command(flatten)]
+    pub ranking: RankingArgs,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ImageArgs {
+    /// Frames of animated images used: first, all or the first N
+    #[arg(long, default_value_t = Frames::First)]
+    pub frames: Frames,
+
+    /// Only use pixels inside a rectangle, given as x,y,w,h
+    #[arg(long, conflicts_with = "region")]
+    pub crop: Option<Crop>,
+
+    /// Only use pixels inside a part of the image
+    #[arg(long, value_enum)]
+    pub region: Option<RegionPreset>,
+
+    /// Weight pixels by the alpha channel (or luminance) of a mask image
+    #[arg(long)]
+    pub mask: Option<String>,
+
+    /// Favour the subject of the image over a plain background
+    #[arg(long, default_value_t = false)]
+    pub saliency: bool,
+
+    /// Always decode images instead of reusing their cached palettes
+    #[arg(long, default_value_t = false)]
+    pub no_cache: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RankingArgs {
+    /// Number of pokemon returned
+    #[arg(short, long, default_value_t = DEFAULT_TOP_K)]
+    pub top_k: usize,
+
+    /// Collapse results to the best scoring variants
+    #[arg(long, value_enum, default_value_t = Distinct::None)]
+    pub distinct: Distinct,
+
+    /// Maximum number of results per species
+    #[arg(long)]
+    pub max_per_species: Option<NonZeroUsize>,
+
+    /// Trade similarity for variety among results (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.0, value_parser = parse_unit_interval)]
+    pub diversity: f32,
+
+    /// Which parts of colors are compared
+    #[arg(long = "match", value_enum, default_value_t = MatchMode::Full)]
+    pub match_mode: MatchMode,
+
+    /// Color space colors are compared in
+    #[arg(long, value_enum, default_value_t = Space::Lab)]
+    pub space: Space,
+
+    /// Match pokemon in a color harmony with the image instead of similar ones
+    #[arg(long, value_enum)]
+    pub harmony: Option<Harmony>,
+
+    /// Background sprites are shown on, as hex color or auto-from-image. Sprites
+    /// blending into it rank lower.
+    #[arg(long)]
+    pub background: Option<Background>,
+
+    /// Favour saturated colors over greys, 0.0 weights all colors equally
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    pub accent: f32,
+
+    /// Exponent of the accent weighting, higher values only boost the most
+    /// saturated colors
+    #[arg(long, default_value_t = 1.0, value_parser = parse_non_negative)]
+    pub accent_curve: f32,
+
+    /// Score base and accent colors separately
+    #[arg(long, default_value_t = false)]
+    pub split_accents: bool,
+
+    /// Match colors as seen with a color vision deficiency
+    #[arg(long, value_enum)]
+    pub cvd: Option<Deficiency>,
+
+    /// Severity of the color vision deficiency (0.0 - 1.0)
+    #[arg(long, default_value_t = 1.0, value_parser = parse_unit_interval, requires = "cvd")]
+    pub severity: f32,
+
+    /// Return all of the top matches or one at random, weighted by similarity
+    #[arg(long, value_enum, default_value_t = Pick::All)]
+    pub pick: Pick,
+
+    /// Spread of random picks, in units of palette distance. Higher values
+    /// give less similar matches more chances, 0.0 picks the best.
+    #[arg(long, default_value_t = 5.0, value_parser = parse_non_negative)]
+    pub temperature: f32,
+
+    /// Seed random picks to get the same ones every run
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(
+    group(ArgGroup::new("shiny-filter")
+        .args(["no_shiny", "all_shiny"])
+        .multiple(false)),
+    group(ArgGroup::new("mega-filter")
+        .args(["no_mega", "all_mega"])
+        .multiple(false)),
+    group(ArgGroup::new("gmax-filter")
+        .args(["no_gmax", "all_gmax"])
+        .multiple(false)),
+    group(ArgGroup::new("female-filter")
+        .args(["no_female", "all_female"])
+        .multiple(false)),
+)]
+pub struct FilterArgs {
     #[arg(short, long)]
     pub gen: Option<String>,
 
@@ -56,4 +346,220 @@ pub struct Args {
     /// Filter out regional variants
     #[arg(long, default_value_t = false)]
     pub no_regional: bool,
+
+    /// Filter out female variants
+    #[arg(long, default_value_t = false)]
+    pub no_female: bool,
+
+    /// Filter out non-female variants
+    #[arg(long, default_value_t = false)]
+    pub all_female: bool,
+}
+
+impl MatchArgs {
+    pub fn palette_matcher(&self) -> Result<PaletteMatcher> {
+        let mut filter = self.filters.sprite_filter()?;
+        if let Some(form) = &self.form {
+            filter = filter.variant(form);
+        }
+
+        self.ranking
+            .palette_matcher(filter)?
+            .focus(self.image.focus()?)
+            .build()
+    }
+}
+
+impl ImageArgs {
+    pub fn focus(&self) -> Result<Focus> {
+        let region = match (self.crop, self.region) {
+            (Some(crop), _) => Some(Region::Crop(crop)),
+            (None, Some(preset)) => Some(Region::Preset(preset)),
+            (None, None) => None,
+        };
+
+        let mask = match &self.mask {
+            Some(path) => Some(Focus::mask_from_image(&open_image(path)?)),
+            None => None,
+        };
+
+        Ok(Focus {
+            region,
+            mask,
+            saliency: self.saliency,
+        })
+    }
+
+    /// Palette cache, unless disabled
+    pub fn cache(&self) -> Option<PaletteCache> {
+        if self.no_cache {
+            None
+        } else {
+            PaletteCache::open()
+        }
+    }
+}
+
+impl RankingArgs {
+    pub fn palette_matcher(&self, filter: SpriteFilter) -> Result<PaletteMatcherBuilder> {
+        Ok(PaletteMatcher::builder()
+            .database(PokemonDatabase::load()?)
+            .filter(filter)
+            .metric(self.metric())
+            .harmony(self.harmony)
+            .background(self.background)
+            .top_k(self.top_k)
+            .distinct(self.distinct)
+            .max_per_species(self.max_per_species)
+            .diversity(self.diversity))
+    }
+}
+
+impl RankingArgs {
+    /// Random number generator for `--pick random`, seeded if asked
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        }
+    }
+
+    /// The matches to show: all of them, or one picked at random
+    pub fn pick<'a>(&self, top: Vec<Match<'a>>, rng: &mut StdRng) -> Vec<Match<'a>> {
+        match self.pick {
+            Pick::All => top,
+            Pick::Random => {
+                let distances: Vec<f32> = top.iter().map(|m| m.distance).collect();
+                pick_weighted(&distances, self.temperature, rng)
+                    .map(|i| vec![top[i]])
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    fn metric(&self) -> Metric {
+        let (mode, space) = (self.match_mode, self.space);
+        let mut metric: Metric = Box::new(move |a, b| palette_distance_in(a, b, mode, space));
+        if self.split_accents {
+            metric = Box::new(with_split_accents(metric));
+        }
+        if self.accent > 0.0 {
+            let curve = AccentCurve {
+                strength: self.accent,
+                exponent: self.accent_curve,
+            };
+            metric = Box::new(with_accent_weights(metric, curve));
+        }
+        if let Some(cvd) = self.cvd() {
+            metric = Box::new(with_cvd(metric, cvd));
+        }
+        metric
+    }
+
+    pub fn cvd(&self) -> Option<Cvd> {
+        self.cvd.map(|deficiency| Cvd {
+            deficiency,
+            severity: self.severity,
+        })
+    }
+}
+
+impl FilterArgs {
+    pub fn sprite_filter(&self) -> Result<SpriteFilter> {
+        let mut filter = SpriteFilter::new();
+
+        if let Some(gen_str) = &self.gen {
+            filter = filter.gen(parse_generation(gen_str)?);
+        }
+        if self.no_shiny || self.all_shiny {
+            filter = filter.shiny(self.all_shiny);
+        }
+        if self.no_mega || self.all_mega {
+            filter = filter.mega(self.all_mega);
+        }
+        if self.no_gmax || self.all_gmax {
+            filter = filter.gmax(self.all_gmax);
+        }
+        if self.no_regional {
+            filter = filter.regional(false);
+        }
+        if self.no_female || self.all_female {
+            filter = filter.female(self.all_female);
+        }
+
+        Ok(filter)
+    }
+}
+
+fn parse_unit_interval(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("Invalid number: {s}"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{value} is out of range (must be 0.0-1.0)"));
+    }
+    Ok(value)
+}
+
+fn parse_non_negative(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("Invalid number: {s}"))?;
+    if value.is_nan() || value < 0.0 {
+        return Err(format!("{value} must not be negative"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_args_definition() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_subcommand_takes_precedence() {
+        let args = Args::try_parse_from(["pokepalette", "similar", "pikachu", "-s"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Similar(ref similar)) if similar.shiny));
+    }
+
+    #[test]
+    fn test_max_per_species_must_be_positive() {
+        assert!(Args::try_parse_from(["pokepalette", "a.png", "--max-per-species", "0"]).is_err());
+        let args =
+            Args::try_parse_from(["pokepalette", "a.png", "--max-per-species", "2"]).unwrap();
+        assert_eq!(args.matching.ranking.max_per_species, NonZeroUsize::new(2));
+    }
+
+    #[test]
+    fn test_unit_interval() {
+        assert_eq!(parse_unit_interval("0.5"), Ok(0.5));
+        assert_eq!(
+            parse_unit_interval("1.5"),
+            Err("1.5 is out of range (must be 0.0-1.0)".to_string())
+        );
+        assert!(parse_unit_interval("high").is_err());
+    }
+
+    #[test]
+    fn test_explain_refuses_other_metrics() {
+        assert!(Args::try_parse_from(["pokepalette", "a.png", "--explain"]).is_ok());
+        let options = [
+            ["--space", "oklab"],
+            ["--harmony", "complementary"],
+            ["--format", "json"],
+        ];
+        for option in options {
+            let argv = ["pokepalette", "a.png", "--explain", option[0], option[1]];
+            assert!(Args::try_parse_from(argv).is_err());
+        }
+        let argv = [
+            "pokepalette",
+            "a.png",
+            "--explain",
+            "--background",
+            "#ffffff",
+        ];
+        assert!(Args::try_parse_from(argv).is_err());
+    }
 }
@@ -0,0 +1,192 @@
+use crate::cli::xdg;
+use crate::colorquant::{Frames, WeightedColor};
+use crate::PaletteMatcher;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const PALETTES_DIR: &str = "palettes";
+
+/// Image palettes stored on disk, so images are only decoded and quantized
+/// again when they or the settings change
+pub struct PaletteCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    palette: Vec<WeightedColor>,
+}
+
+impl PaletteCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache in `$XDG_CACHE_HOME/pokepalette/palettes`
+    pub fn open() -> Option<Self> {
+        xdg::cache_dir().map(|dir| Self::new(dir.join(PALETTES_DIR)))
+    }
+
+    /// Cached palette of an image, if it has not changed since
+    pub fn get(&self, key: &str) -> Option<Vec<WeightedColor>> {
+        let bytes = fs::read(self.entry_path(key)).ok()?;
+        let (entry, _): (Entry, _) =
+            bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).ok()?;
+
+        // Guards against hash collisions
+        (entry.key == key).then_some(entry.palette)
+    }
+
+    pub fn insert(&self, key: &str, palette: &[WeightedColor]) -> Result<()> {
+        let entry = Entry {
+            key: key.to_string(),
+            palette: palette
+                .iter()
+                .map(|wc| WeightedColor {
+                    color: wc.color,
+                    freq: wc.freq,
+                })
+                .collect(),
+        };
+        let bytes = bincode::serde::encode_to_vec(&entry, bincode::config::standard())?;
+
+        // Images are matched in parallel, so write aside and rename to never
+        // leave a half written entry
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(key);
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Remove every cached palette, returning how many there were
+    pub fn clear(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.bin", hasher.finish()))
+    }
+}
+
+/// Key of an image file's palette: its path, modification time and size,
+/// plus the settings the palette is extracted with. `None` if the file has
+/// no metadata to tell changes apart, e.g. stdin.
+pub fn palette_key(matcher: &PaletteMatcher, path: &Path, frames: Frames) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let metadata = fs::metadata(&path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+
+    Some(format!(
+        "{} {}\n{}\nmodified={} size={}\nframes={:?} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        path.display(),
+        modified,
+        metadata.len(),
+        frames,
+        matcher.palette_key(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pokemondatabase::PokemonDatabase;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pokepalette-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_palettes_round_trip_until_cleared() {
+        let cache = PaletteCache::new(temp_dir("cache-round-trip"));
+        let palette = vec![
+            WeightedColor {
+                color: [0, 64, 128],
+                freq: 0.75,
+            },
+            WeightedColor {
+                color: [255, 255, 255],
+                freq: 0.25,
+            },
+        ];
+
+        assert!(cache.get("a").is_none());
+        cache.insert("a", &palette).unwrap();
+        let cached = cache.get("a").unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[0].color, [0, 64, 128]);
+        assert_eq!(cached[1].freq, 0.25);
+        assert!(cache.get("b").is_none());
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_key_changes_with_file_and_settings() {
+        let path = temp_dir("cache-key").join("image.png");
+        fs::write(&path, b"one").unwrap();
+
+        let matcher = |saliency| {
+            let focus = crate::colorquant::Focus {
+                saliency,
+                ..Default::default()
+            };
+            PaletteMatcher::builder()
+                .database(PokemonDatabase::from(Vec::new()))
+                .focus(focus)
+                .build()
+                .unwrap()
+        };
+        let (plain, salient) = (matcher(false), matcher(true));
+
+        let key = palette_key(&plain, &path, Frames::First).unwrap();
+        assert_eq!(palette_key(&plain, &path, Frames::First), Some(key.clone()));
+        assert_ne!(
+            palette_key(&salient, &path, Frames::First),
+            Some(key.clone())
+        );
+        assert_ne!(palette_key(&plain, &path, Frames::All), Some(key.clone()));
+
+        fs::write(&path, b"longer").unwrap();
+        assert_ne!(palette_key(&plain, &path, Frames::First), Some(key));
+        assert!(palette_key(&plain, Path::new("-"), Frames::First).is_none());
+    }
+}
@@ -0,0 +1,20 @@
+use crate::cli::args::CacheCommand;
+use crate::cli::cache::PaletteCache;
+use anyhow::{anyhow, Result};
+
+pub fn run(command: &CacheCommand) -> Result<()> {
+    let cache = PaletteCache::open().ok_or_else(|| anyhow!("No cache directory found"))?;
+
+    match command {
+        CacheCommand::Clear => {
+            let removed = cache.clear()?;
+            println!(
+                "Removed {} cached palettes from {}",
+                removed,
+                cache.dir().display()
+            );
+        }
+    }
+
+    Ok(())
+}
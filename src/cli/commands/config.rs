@@ -0,0 +1,25 @@
+use crate::cli::args::{Args, ConfigCommand};
+use crate::cli::config::{self, Config};
+use anyhow::Result;
+
+pub fn run(args: &Args, command: &ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Show => show(args),
+    }
+}
+
+fn show(args: &Args) -> Result<()> {
+    let config = Config::load(args.config.as_deref())?;
+    let settings = config.settings(args.preset.as_deref())?;
+
+    match &config.path {
+        Some(path) => println!("# {}", path.display()),
+        None => println!("# no config file, showing defaults"),
+    }
+    if let Some(preset) = &args.preset {
+        println!("# preset: {}", preset);
+    }
+    println!("{}", config::effective_settings(&settings));
+
+    Ok(())
+}
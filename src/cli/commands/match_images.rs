@@ -0,0 +1,92 @@
+use crate::cli::args::Args;
+use crate::cli::input::{expand_inputs, load_palette};
+use crate::cli::output::{
+    print_csv_header, print_csv_result, print_explanations, print_image_header,
+    print_image_information, print_json_error, print_json_result, print_result,
+    print_simulated_information, print_top_information, OutputFormat,
+};
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+
+pub fn run(args: &Args) -> Result<()> {
+    // Load database and filter
+    let matcher = args.matching.palette_matcher()?;
+
+    let paths = expand_inputs(&args.images, args.recursive)?;
+    let cache = args.matching.image.cache();
+
+    // Generate image palettes and match them in parallel, keeping input order
+    let results: Vec<_> = paths
+        .par_iter()
+        .map(|path| {
+            let image_palette =
+                load_palette(&matcher, path, args.matching.image.frames, cache.as_ref())?;
+            let top = matcher.match_palette(&image_palette);
+            Ok((image_palette, top))
+        })
+        .collect();
+
+    // Random picks happen in input order, so seeded runs pick the same
+    let mut rng = args.matching.ranking.rng();
+
+    // Print results
+    if args.format == OutputFormat::Csv {
+        print_csv_header();
+    }
+
+    let mut failed = 0;
+    let mut printed = 0;
+    for (path, result) in paths.iter().zip(results) {
+        let image = path.display().to_string();
+
+        let (image_palette, top) = match result {
+            Ok((image_palette, top)) => (image_palette, args.matching.ranking.pick(top, &mut rng)),
+            Err(err) => {
+                failed += 1;
+                match args.format {
+                    OutputFormat::Json => print_json_error(&image, &err),
+                    _ => eprintln!("{}: {:#}", image, err),
+                }
+                continue;
+            }
+        };
+
+        match args.format {
+            OutputFormat::Text => {
+                if paths.len() > 1 {
+                    if printed > 0 {
+                        println!();
+                    }
+                    print_image_header(&image);
+                }
+
+                if args.verbose {
+                    match args.matching.ranking.cvd() {
+                        Some(cvd) => print_simulated_information(&image_palette, cvd),
+                        None => print_image_information(&image_palette),
+                    }
+                    print_top_information(&top);
+                } else if !args.explain {
+                    print_result(&top);
+                }
+
+                if args.explain {
+                    print_explanations(&image_palette, &top);
+                }
+            }
+            OutputFormat::Json => print_json_result(&image, &top),
+            OutputFormat::Csv => print_csv_result(&image, &top),
+        }
+        printed += 1;
+    }
+
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} images could not be processed",
+            failed,
+            paths.len()
+        ));
+    }
+
+    Ok(())
+}
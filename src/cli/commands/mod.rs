@@ -0,0 +1,6 @@
+pub mod cache;
+pub mod config;
+pub mod match_images;
+pub mod similar;
+pub mod wallpaper_for;
+pub mod watch;
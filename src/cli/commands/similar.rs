@@ -0,0 +1,89 @@
+use crate::cli::args::SimilarArgs;
+use crate::cli::output::{print_result, print_top_information};
+use crate::matcher::Match;
+use crate::pokemondatabase::SpriteFilter;
+use crate::PaletteMatcher;
+use anyhow::{anyhow, Result};
+
+pub fn run(args: &SimilarArgs) -> Result<()> {
+    let matcher = args
+        .ranking
+        .palette_matcher(without_query(args.filters.sprite_filter()?, args))?
+        .build()?;
+
+    let top = args
+        .ranking
+        .pick(similar(&matcher, args)?, &mut args.ranking.rng());
+
+    if args.verbose {
+        print_top_information(&top);
+    } else {
+        print_result(&top);
+    }
+
+    Ok(())
+}
+
+/// The sprite is its own best match, so it is left out of the candidates
+/// before `--distinct` and `--diversity` rank them
+fn without_query(filter: SpriteFilter, args: &SimilarArgs) -> SpriteFilter {
+    filter.and(!SpriteFilter::new().krabby(&args.name, args.shiny, args.form.as_deref()))
+}
+
+fn similar<'a>(matcher: &'a PaletteMatcher, args: &SimilarArgs) -> Result<Vec<Match<'a>>> {
+    // Look up in the whole database, the sprite itself is filtered out
+    let sprite = matcher
+        .database()
+        .find(&args.name, args.shiny, args.form.as_deref())
+        .ok_or_else(|| anyhow!("No sprite found for {}", args.name))?;
+
+    Ok(matcher.match_palette(&sprite.palette))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::args::{Args, Command};
+    use crate::pokemondatabase::PokemonDatabase;
+    use crate::sprite::Sprite;
+    use clap::Parser;
+
+    fn similar_args(argv: &[&str]) -> SimilarArgs {
+        let argv = ["pokepalette", "similar"].iter().chain(argv);
+        match Args::try_parse_from(argv).unwrap().command {
+            Some(Command::Similar(args)) => args,
+            _ => panic!("expected the similar subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_query_is_excluded_before_diversity() {
+        let args = similar_args(&["charmander", "-t", "2", "--diversity", "0.5"]);
+        let database = PokemonDatabase::from(vec![
+            Sprite::named("charmander").color([240, 128, 48]),
+            Sprite::named("vulpix").color([230, 120, 60]),
+            Sprite::named("growlithe").color([235, 125, 55]),
+            Sprite::named("squirtle").color([96, 160, 224]),
+        ]);
+        let matcher = PaletteMatcher::builder()
+            .database(database)
+            .filter(without_query(SpriteFilter::new(), &args))
+            .top_k(args.ranking.top_k)
+            .diversity(args.ranking.diversity)
+            .build()
+            .unwrap();
+
+        // The best match among the candidates leads, the query is not one
+        let names: Vec<&str> = similar(&matcher, &args)
+            .unwrap()
+            .iter()
+            .map(|m| m.sprite.name.as_str())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0], "growlithe");
+        assert!(!names.contains(&"charmander"));
+
+        let args = similar_args(&["mew"]);
+        assert!(similar(&matcher, &args).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use crate::cli::args::WallpaperForArgs;
+use crate::cli::input::{expand_inputs, load_palette};
+use crate::cli::output::format_score;
+use crate::pokemondatabase::PokemonDatabase;
+use crate::PaletteMatcher;
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+
+pub fn run(args: &WallpaperForArgs) -> Result<()> {
+    let matcher = PaletteMatcher::builder()
+        .database(PokemonDatabase::load()?)
+        .focus(args.image.focus()?)
+        .build()?;
+
+    let sprite = matcher
+        .database()
+        .find(&args.name, args.shiny, args.form.as_deref())
+        .ok_or_else(|| anyhow!("No sprite found for {}", args.name))?;
+
+    let paths = expand_inputs(&args.images, args.recursive)?;
+    let cache = args.image.cache();
+
+    let palettes: Vec<_> = paths
+        .par_iter()
+        .map(|path| load_palette(&matcher, path, args.image.frames, cache.as_ref()))
+        .collect();
+
+    // Unreadable images are skipped, the rest are ranked against the sprite
+    let mut readable = Vec::new();
+    for (path, palette) in paths.iter().zip(palettes) {
+        match palette {
+            Ok(palette) => readable.push((path, palette)),
+            Err(err) => eprintln!("{}: {:#}", path.display(), err),
+        }
+    }
+
+    let ranked = matcher.rank(
+        &sprite.palette,
+        readable
+            .iter()
+            .map(|(path, palette)| (*path, palette.as_slice())),
+    );
+
+    for (path, distance) in ranked.into_iter().take(args.top_k) {
+        if args.verbose {
+            println!("{}\n{}\n", path.display(), format_score(distance));
+        } else {
+            println!("{}", path.display());
+        }
+    }
+
+    Ok(())
+}
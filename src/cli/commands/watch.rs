@@ -0,0 +1,232 @@
+use crate::cli::args::WatchArgs;
+use crate::cli::cache::PaletteCache;
+use crate::cli::input::load_palette;
+use crate::cli::output::{format_result, sprite_flags};
+use crate::sprite::Sprite;
+use crate::PaletteMatcher;
+use anyhow::{anyhow, Context, Result};
+use image::ImageFormat;
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use rand::rngs::StdRng;
+use std::fs;
+use std::path::{self, Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// Files holding the path of an image are a line of text, anything longer
+// is taken for the image itself
+const MAX_PATH_FILE_SIZE: u64 = 4096;
+
+pub fn run(args: &WatchArgs) -> Result<()> {
+    // The database stays loaded between matches
+    let matcher = args.matching.palette_matcher()?;
+    let cache = args.matching.image.cache();
+    let mut rng = args.matching.ranking.rng();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(Duration::from_millis(args.debounce), sender)?;
+    let mut watched_dirs: Vec<PathBuf> = Vec::new();
+
+    let path = path::absolute(&args.path)?;
+    let mut image = None;
+
+    loop {
+        // Symlinks and path files can point to a new image on every change,
+        // so the directory of the current image is watched as well
+        image = match_and_report(args, &matcher, cache.as_ref(), &mut rng, &path).or(image);
+
+        let dirs = [Some(&path), image.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|file| file.parent().map(Path::to_path_buf));
+        for dir in dirs {
+            if !watched_dirs.contains(&dir) {
+                debouncer
+                    .watcher()
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch {}", dir.display()))?;
+                watched_dirs.push(dir);
+            }
+        }
+
+        // Wait for a change to the path or the image it points to
+        loop {
+            let events = receiver
+                .recv()?
+                .map_err(|err| anyhow!("Failed to watch {}: {}", path.display(), err))?;
+            if events
+                .iter()
+                .any(|event| event.path == path || Some(&event.path) == image.as_ref())
+            {
+                break;
+            }
+        }
+    }
+}
+
+/// Match the image behind `path` and hand the best match on, returning the
+/// image. Errors are printed rather than returned to keep watching.
+fn match_and_report(
+    args: &WatchArgs,
+    matcher: &PaletteMatcher,
+    cache: Option<&PaletteCache>,
+    rng: &mut StdRng,
+    path: &Path,
+) -> Option<PathBuf> {
+    let image = match resolve_image(path) {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("{}: {:#}", path.display(), err);
+            return None;
+        }
+    };
+
+    if let Err(err) = match_image(args, matcher, cache, rng, &image) {
+        eprintln!("{}: {:#}", image.display(), err);
+    }
+    Some(image)
+}
+
+fn match_image(
+    args: &WatchArgs,
+    matcher: &PaletteMatcher,
+    cache: Option<&PaletteCache>,
+    rng: &mut StdRng,
+    image: &Path,
+) -> Result<()> {
+    let palette = load_palette(matcher, image, args.matching.image.frames, cache)?;
+    let top = args
+        .matching
+        .ranking
+        .pick(matcher.match_palette(&palette), rng);
+    let best = top
+        .first()
+        .ok_or_else(|| anyhow!("No pokemon left after filtering"))?;
+
+    if let Some(output) = &args.output {
+        fs::write(output, format_result(best.sprite) + "\n")
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+    }
+
+    if let Some(template) = &args.exec {
+        let argv = exec_command(template, best.sprite, image)?;
+        let status = process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .status()
+            .with_context(|| format!("Failed to run {}", argv[0]))?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {}", argv[0], status));
+        }
+    }
+
+    if args.output.is_none() && args.exec.is_none() {
+        println!("{}", format_result(best.sprite));
+    }
+
+    Ok(())
+}
+
+/// Image a watched path stands for: the file a symlink points to, or the
+/// path written in a text file like pywal's `~/.cache/wal/wal`
+fn resolve_image(path: &Path) -> Result<PathBuf> {
+    let resolved = fs::canonicalize(path)?;
+    if ImageFormat::from_path(&resolved).is_ok()
+        || fs::metadata(&resolved)?.len() > MAX_PATH_FILE_SIZE
+    {
+        return Ok(resolved);
+    }
+
+    let target = fs::read_to_string(&resolved)
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .next()
+                .map(|line| PathBuf::from(line.trim()))
+        })
+        .filter(|target| target.is_absolute() && target.is_file());
+
+    match target {
+        Some(target) => Ok(fs::canonicalize(target)?),
+        None => Ok(resolved),
+    }
+}
+
+/// Split the `--exec` template into arguments and fill in the match. A
+/// `{flags}` argument of its own becomes one argument per flag.
+fn exec_command(template: &str, sprite: &Sprite, image: &Path) -> Result<Vec<String>> {
+    let words = shell_words::split(template)
+        .with_context(|| format!("Invalid command template: {}", template))?;
+    if words.is_empty() {
+        return Err(anyhow!("Empty command template"));
+    }
+
+    let flags = sprite_flags(sprite);
+    let mut argv = Vec::new();
+    for word in words {
+        if word == "{flags}" {
+            argv.extend(flags.iter().cloned());
+        } else {
+            argv.push(
+                word.replace("{name}", &sprite.name)
+                    .replace("{flags}", &flags.join(" "))
+                    .replace("{image}", &image.display().to_string()),
+            );
+        }
+    }
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::Form;
+
+    #[test]
+    fn test_exec_command_fills_in_match() {
+        let image = Path::new("/walls/sunset one.png");
+
+        let argv = exec_command(
+            "krabby name {name} {flags}",
+            &Sprite::named("charizard")
+                .shiny(true)
+                .form(Form::from("mega-x")),
+            image,
+        )
+        .unwrap();
+        assert_eq!(argv, ["krabby", "name", "charizard", "-s", "-f", "mega-x"]);
+
+        let argv = exec_command(
+            "notify-send '{name} for {image}'",
+            &Sprite::named("charizard"),
+            image,
+        )
+        .unwrap();
+        assert_eq!(argv, ["notify-send", "charizard for /walls/sunset one.png"]);
+
+        assert!(exec_command("  ", &Sprite::named("charizard"), image).is_err());
+    }
+
+    #[test]
+    fn test_resolve_image_follows_path_files() {
+        let dir = std::env::temp_dir().join(format!("pokepalette-watch-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("wall.png");
+        fs::write(&image, b"not really a png").unwrap();
+        let image = fs::canonicalize(image).unwrap();
+
+        let path_file = dir.join("wal");
+        fs::write(&path_file, format!("{}\n", image.display())).unwrap();
+        assert_eq!(resolve_image(&path_file).unwrap(), image);
+        assert_eq!(resolve_image(&image).unwrap(), image);
+
+        // Text that is no path is taken for the image itself
+        fs::write(&path_file, "hello").unwrap();
+        assert_eq!(
+            resolve_image(&path_file).unwrap(),
+            fs::canonicalize(&path_file).unwrap()
+        );
+    }
+}
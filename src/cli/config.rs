@@ -0,0 +1,400 @@
+use crate::cli::args::Args;
+use crate::cli::xdg;
+use anyhow::{anyhow, Context, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Arguments that only make sense on the command line
+const CLI_ONLY: [&str; 4] = ["config", "preset", "help", "version"];
+
+/// Settings from the config file. Keys are long option names with
+/// underscores, or the names of the fields of `Args` where those differ.
+/// `[preset.<name>]` sections hold named sets of settings.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// File the config was read from, if any
+    pub path: Option<PathBuf>,
+    settings: Table,
+    presets: Table,
+}
+
+impl Config {
+    /// Load the given config file, or the one in the XDG config directory if
+    /// it exists
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let mut config =
+            Self::parse(&content).with_context(|| format!("Invalid config {}", path.display()))?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut settings: Table = content.parse()?;
+        let presets = match settings.remove("preset") {
+            Some(Value::Table(presets)) => presets,
+            Some(_) => {
+                return Err(anyhow!(
+                    "preset must be a table of [preset.<name>] sections"
+                ))
+            }
+            None => Table::new(),
+        };
+
+        Ok(Self {
+            path: None,
+            settings,
+            presets,
+        })
+    }
+
+    /// Top-level settings, overridden by those of the preset, keyed by
+    /// argument id
+    pub fn settings(&self, preset: Option<&str>) -> Result<Table> {
+        let command = Args::command();
+        let mut settings = argument_keys(&command, &self.settings)?;
+
+        if let Some(name) = preset {
+            let Some(Value::Table(preset)) = self.presets.get(name) else {
+                let names: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+                return Err(anyhow!(
+                    "No preset named {} (available: {})",
+                    name,
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                ));
+            };
+            settings.extend(argument_keys(&command, preset)?);
+        }
+
+        Ok(settings)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/pokepalette/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    xdg::config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+/// Parse the command line, using config values for arguments that were not
+/// given. Exits on invalid arguments like `Args::parse`.
+pub fn parse_args() -> Result<Args> {
+    parse_args_from(std::env::args_os().collect())
+}
+
+pub fn parse_args_from(argv: Vec<OsString>) -> Result<Args> {
+    let command = Args::command();
+    let argv = move_global_options(&command, argv);
+    let matches = command
+        .clone()
+        .try_get_matches_from(&argv)
+        .unwrap_or_else(|err| err.exit());
+
+    let config = Config::load(matches.get_one::<PathBuf>("config").map(PathBuf::as_path))?;
+    let settings = config.settings(matches.get_one::<String>("preset").map(String::as_str))?;
+
+    // Subcommands take the settings they have arguments for
+    let (target, target_matches) = match matches.subcommand() {
+        Some((name, sub_matches)) => match command.find_subcommand(name) {
+            Some(subcommand) => (subcommand, sub_matches),
+            None => (&command, &matches),
+        },
+        None => (&command, &matches),
+    };
+
+    let extra = config_args(target, target_matches, &settings);
+    if extra.is_empty() {
+        return Ok(Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()));
+    }
+
+    // Config values go before a `--`, everything after it is positional
+    let mut argv = argv;
+    let position = argv
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(argv.len());
+    argv.splice(position..position, extra);
+
+    Ok(Args::try_parse_from(argv).unwrap_or_else(|err| err.exit()))
+}
+
+/// Options given before anything else make clap read the subcommand as an
+/// image, so `--config a.toml similar pikachu` becomes
+/// `similar --config a.toml pikachu`
+fn move_global_options(command: &clap::Command, argv: Vec<OsString>) -> Vec<OsString> {
+    let mut end = 1;
+    while let Some(arg) = argv.get(end).and_then(|arg| arg.to_str()) {
+        if arg == "--config" || arg == "--preset" {
+            end += 2;
+        } else if arg.starts_with("--config=") || arg.starts_with("--preset=") {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+
+    let is_subcommand = argv
+        .get(end)
+        .and_then(|arg| arg.to_str())
+        .is_some_and(|name| command.find_subcommand(name).is_some());
+    if end == 1 || !is_subcommand {
+        return argv;
+    }
+
+    let mut argv = argv;
+    argv[1..=end].rotate_right(1);
+    argv
+}
+
+/// Settings keyed by the id of the argument they set. Keys must name an
+/// argument of the main command or of a subcommand.
+fn argument_keys(command: &clap::Command, settings: &Table) -> Result<Table> {
+    let mut keyed = Table::new();
+    for (key, value) in settings {
+        let arg = find_config_arg(command, key).or_else(|| {
+            command
+                .get_subcommands()
+                .find_map(|subcommand| find_config_arg(subcommand, key))
+        });
+        match arg {
+            None => return Err(anyhow!("Unknown config key: {}", key)),
+            Some(arg) if arg.is_positional() || CLI_ONLY.contains(&arg.get_id().as_str()) => {
+                return Err(anyhow!("{} cannot be set in the config", key))
+            }
+            Some(arg) => {
+                keyed.insert(arg.get_id().to_string(), value.clone());
+            }
+        }
+    }
+    Ok(keyed)
+}
+
+/// Command line arguments for the settings that were not given and do not
+/// conflict with given arguments
+fn config_args(command: &clap::Command, matches: &ArgMatches, settings: &Table) -> Vec<OsString> {
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+    let mut args = Vec::new();
+    for (key, value) in settings {
+        let Some(arg) = find_arg(command, key) else {
+            continue;
+        };
+        if given(key) || conflicts(command, arg).iter().any(|id| given(id)) {
+            continue;
+        }
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+
+        match value {
+            Value::Boolean(true) => args.push(format!("--{long}").into()),
+            Value::Boolean(false) => {}
+            Value::Array(values) => args.extend(
+                values
+                    .iter()
+                    .map(|v| format!("--{long}={}", plain(v)).into()),
+            ),
+            value => args.push(format!("--{long}={}", plain(value)).into()),
+        }
+    }
+    args
+}
+
+/// Arguments that cannot be used together with `arg`
+fn conflicts(command: &clap::Command, arg: &Arg) -> Vec<String> {
+    let mut ids: Vec<String> = command
+        .get_arg_conflicts_with(arg)
+        .iter()
+        .map(|other| other.get_id().to_string())
+        .collect();
+
+    for group in command.get_groups() {
+        let mut group = group.clone();
+        let members: Vec<String> = group.get_args().map(|id| id.to_string()).collect();
+        if !group.is_multiple() && members.iter().any(|id| id == arg.get_id()) {
+            ids.extend(members.into_iter().filter(|id| id != arg.get_id()));
+        }
+    }
+
+    ids
+}
+
+fn find_arg<'a>(command: &'a clap::Command, id: &str) -> Option<&'a Arg> {
+    command.get_arguments().find(|arg| arg.get_id() == id)
+}
+
+/// Argument a config key sets, by its long name or its id
+fn find_config_arg<'a>(command: &'a clap::Command, key: &str) -> Option<&'a Arg> {
+    command
+        .get_arguments()
+        .find(|arg| config_key(arg) == key)
+        .or_else(|| find_arg(command, key))
+}
+
+/// Long option name with underscores, e.g. `accent_curve` for `--accent-curve`
+fn config_key(arg: &Arg) -> String {
+    arg.get_long()
+        .map_or_else(|| arg.get_id().to_string(), |long| long.replace('-', "_"))
+}
+
+/// Value as typed on the command line, strings without quotes
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Effective settings of every configurable argument as TOML, with defaults
+/// commented as such
+pub fn effective_settings(settings: &Table) -> String {
+    let command = Args::command();
+    let mut lines = Vec::new();
+
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if arg.is_positional() || CLI_ONLY.contains(&id) {
+            continue;
+        }
+
+        let key = config_key(arg);
+        match settings.get(id) {
+            Some(value) => lines.push(format!("{key} = {value}")),
+            None => match default_value(arg) {
+                Some(value) => lines.push(format!("{key} = {value}  # default")),
+                None => lines.push(format!("# {key} =")),
+            },
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn default_value(arg: &Arg) -> Option<Value> {
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+        return Some(Value::Boolean(false));
+    }
+
+    let default = arg.get_default_values().first()?.to_string_lossy();
+    Some(if let Ok(int) = default.parse::<i64>() {
+        Value::Integer(int)
+    } else if let Ok(float) = default.parse::<f64>() {
+        Value::Float(float)
+    } else {
+        Value::String(default.into_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colorquant::MatchMode;
+
+    const CONFIG: &str = r#"
+top_k = 5
+gen = "1-4"
+no_gmax = true
+
+[preset.shiny]
+all_shiny = true
+top_k = 3
+"#;
+
+    fn parse_with_config(name: &str, content: &str, args: &[&str]) -> Result<Args> {
+        let path = std::env::temp_dir().join(format!(
+            "pokepalette-config-{}-{}.toml",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, content)?;
+
+        let mut argv: Vec<OsString> = vec!["pokepalette".into(), "--config".into(), path.into()];
+        argv.extend(args.iter().map(OsString::from));
+        parse_args_from(argv)
+    }
+
+    #[test]
+    fn test_preset_overrides_settings() {
+        let config = Config::parse(CONFIG).unwrap();
+        let settings = config.settings(Some("shiny")).unwrap();
+        assert_eq!(settings["top_k"].as_integer(), Some(3));
+        assert_eq!(settings["gen"].as_str(), Some("1-4"));
+        assert!(config.settings(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_config_values_fill_in_args() {
+        let args = parse_with_config("fill", CONFIG, &["image.png"]).unwrap();
+        assert_eq!(args.matching.ranking.top_k, 5);
+        assert_eq!(args.matching.filters.gen.as_deref(), Some("1-4"));
+        assert!(args.matching.filters.no_gmax);
+    }
+
+    #[test]
+    fn test_cli_overrides_config() {
+        let args =
+            parse_with_config("override", CONFIG, &["image.png", "-t", "8", "--all-gmax"]).unwrap();
+        assert_eq!(args.matching.ranking.top_k, 8);
+        assert!(args.matching.filters.all_gmax);
+        assert!(!args.matching.filters.no_gmax);
+    }
+
+    #[test]
+    fn test_preset_applies_to_subcommands() {
+        let args = parse_with_config(
+            "similar",
+            CONFIG,
+            &["--preset", "shiny", "similar", "pikachu"],
+        )
+        .unwrap();
+        let Some(crate::cli::args::Command::Similar(similar)) = args.command else {
+            panic!("expected the similar subcommand");
+        };
+        assert_eq!(similar.ranking.top_k, 3);
+        assert!(similar.filters.all_shiny);
+    }
+
+    #[test]
+    fn test_config_show_takes_no_flags() {
+        let argv = ["pokepalette", "config", "show"];
+        assert!(Args::try_parse_from(argv).is_ok());
+        assert!(Args::try_parse_from(["pokepalette", "config", "show", "-t", "9"]).is_err());
+    }
+
+    #[test]
+    fn test_keys_are_long_names_or_ids() {
+        let args = parse_with_config("long", "match = \"hue\"", &["image.png"]).unwrap();
+        assert_eq!(args.matching.ranking.match_mode, MatchMode::Hue);
+        let args = parse_with_config("id", "match_mode = \"hue\"", &["image.png"]).unwrap();
+        assert_eq!(args.matching.ranking.match_mode, MatchMode::Hue);
+
+        let config = Config::parse("match = \"full\"\n[preset.hue]\nmatch_mode = \"hue\"").unwrap();
+        let settings = config.settings(Some("hue")).unwrap();
+        assert_eq!(settings["match_mode"].as_str(), Some("hue"));
+        assert!(effective_settings(&settings).contains("match = \"hue\""));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        assert!(parse_with_config("unknown", "top_kk = 3", &["image.png"]).is_err());
+        assert!(parse_with_config("positional", "images = [\"a.png\"]", &["image.png"]).is_err());
+    }
+}
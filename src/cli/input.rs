@@ -0,0 +1,168 @@
+use crate::cli::cache::{palette_key, PaletteCache};
+use crate::colorquant::{open_frames, read_frames, Frames, WeightedColor, WeightedFrame};
+use crate::PaletteMatcher;
+use anyhow::{anyhow, Result};
+use image::ImageFormat;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Path that stands for stdin
+pub const STDIN_PATH: &str = "-";
+
+/// Expand image arguments into image paths. Arguments can be files,
+/// directories (searched recursively if asked), glob patterns or `-` for stdin.
+pub fn expand_inputs(inputs: &[String], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+        if input == STDIN_PATH {
+            // stdin can only be read once
+            if paths.iter().any(|path| path == Path::new(STDIN_PATH)) {
+                return Err(anyhow!("{} can only be given once", STDIN_PATH));
+            }
+            paths.push(path.to_path_buf());
+        } else if path.is_dir() {
+            collect_dir(path, recursive, &mut paths)?;
+        } else if !path.exists() && is_glob(input) {
+            let mut matched: Vec<PathBuf> = glob::glob(input)?
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .collect();
+            if matched.is_empty() {
+                return Err(anyhow!("No images match {}", input));
+            }
+            matched.sort();
+            paths.extend(matched);
+        } else {
+            // Unreadable files are reported when they are opened
+            paths.push(path.to_path_buf());
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Open the frames of an image path, reading stdin for `-`
+pub fn load_frames(path: &Path, frames: Frames) -> Result<Vec<WeightedFrame>> {
+    if path == Path::new(STDIN_PATH) {
+        read_frames(io::stdin().lock(), frames)
+    } else {
+        open_frames(path, frames)
+    }
+}
+
+fn collect_dir(dir: &Path, recursive: bool, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                collect_dir(&path, recursive, paths)?;
+            }
+        } else if ImageFormat::from_path(&path).is_ok() {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// Palette of an image with the matcher's settings, failing when nothing
+/// of the image is left in focus. Palettes are reused from the cache while
+/// the file and settings stay the same.
+pub fn load_palette(
+    matcher: &PaletteMatcher,
+    path: &Path,
+    frames: Frames,
+    cache: Option<&PaletteCache>,
+) -> Result<Vec<WeightedColor>> {
+    let key = cache.and_then(|_| palette_key(matcher, path, frames));
+    if let (Some(cache), Some(key)) = (cache, &key) {
+        if let Some(palette) = cache.get(key) {
+            return Ok(palette);
+        }
+    }
+
+    let palette = matcher.frames_palette(&load_frames(path, frames)?)?;
+
+    // A cache that cannot be written only costs speed
+    if let (Some(cache), Some(key)) = (cache, &key) {
+        let _ = cache.insert(key, &palette);
+    }
+    Ok(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pokepalette-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for file in ["a.png", "b.jpg", "notes.txt", "sub/c.png"] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        dir
+    }
+
+    fn input(path: &Path) -> String {
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_expand_glob_and_literal_path() {
+        let dir = temp_dir("input-glob");
+
+        let glob = dir.join("*.png").display().to_string();
+        assert_eq!(expand_inputs(&[glob], false).unwrap(), [dir.join("a.png")]);
+
+        // Existing files are taken literally, even with glob characters
+        let literal = dir.join("[x].png");
+        fs::write(&literal, b"").unwrap();
+        assert_eq!(expand_inputs(&[input(&literal)], false).unwrap(), [literal]);
+
+        let unmatched = dir.join("*.webp").display().to_string();
+        assert!(expand_inputs(&[unmatched], false).is_err());
+    }
+
+    #[test]
+    fn test_expand_directories() {
+        let dir = temp_dir("input-dir");
+        let images = [dir.join("a.png"), dir.join("b.jpg")];
+
+        assert_eq!(expand_inputs(&[input(&dir)], false).unwrap(), images);
+        assert_eq!(
+            expand_inputs(&[input(&dir)], true).unwrap(),
+            [images[0].clone(), images[1].clone(), dir.join("sub/c.png")]
+        );
+    }
+
+    #[test]
+    fn test_missing_input_fails_on_its_own() {
+        let dir = temp_dir("input-missing");
+        let missing = dir.join("missing.png");
+
+        let paths = expand_inputs(&[input(&missing), input(&dir.join("a.png"))], false).unwrap();
+        assert_eq!(paths, [missing.clone(), dir.join("a.png")]);
+        assert!(load_frames(&missing, Frames::First).is_err());
+    }
+
+    #[test]
+    fn test_stdin_only_once() {
+        let stdin = STDIN_PATH.to_string();
+        let paths = expand_inputs(&[stdin.clone(), "a.png".to_string()], false).unwrap();
+        assert_eq!(paths, [PathBuf::from(STDIN_PATH), PathBuf::from("a.png")]);
+        assert!(expand_inputs(&[stdin.clone(), stdin], false).is_err());
+    }
+}
@@ -1,2 +1,7 @@
 pub mod args;
+pub mod cache;
+pub mod commands;
+pub mod config;
+pub mod input;
 pub mod output;
+pub mod xdg;
@@ -1,24 +1,51 @@
-use crate::colorquant::WeightedColor;
-use crate::sprite::{Form, Sprite};
+use crate::colorquant::{explain_distance, similarity, ColorPair, Cvd, WeightedColor};
+use crate::matcher::Match;
+use crate::sprite::{Form, Gender, Sprite};
+use clap::ValueEnum;
+use serde_json::json;
 
-pub fn print_result(top: &Vec<(&Sprite, f32)>) {
-    for (sprite, _) in top {
-        print!("{}", sprite.name);
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Krabby-compatible lines, grouped per image
+    Text,
+    /// One JSON object per image
+    Json,
+    /// One row per match
+    Csv,
+}
+
+pub fn print_result(top: &[Match]) {
+    for Match { sprite, .. } in top {
+        println!("{}", format_result(sprite));
+    }
+}
 
-        if sprite.shiny {
-            print!(" -s");
-        }
+/// Krabby arguments for a sprite, e.g. `charizard -s -f mega-x`
+pub fn format_result(sprite: &Sprite) -> String {
+    let mut result = vec![sprite.name.clone()];
+    result.extend(sprite_flags(sprite));
+    result.join(" ")
+}
+
+/// Krabby flags selecting the variant of a sprite, e.g. `-s -f mega-x`
+pub fn sprite_flags(sprite: &Sprite) -> Vec<String> {
+    let mut flags = Vec::new();
 
-        if sprite.form != Form::Regular {
-            print!(" -f {}", sprite.form);
-        }
+    if sprite.shiny {
+        flags.push("-s".to_string());
+    }
 
-        println!();
+    if sprite.form != Form::Regular {
+        flags.extend(["-f".to_string(), sprite.form.to_string()]);
+    } else if sprite.gender == Gender::Female {
+        flags.extend(["-f".to_string(), sprite.gender.to_string()]);
     }
+
+    flags
 }
 
-pub fn print_image_information(image_palette: Vec<WeightedColor>) {
-    for weighted_color in &image_palette {
+pub fn print_image_information(image_palette: &[WeightedColor]) {
+    for weighted_color in image_palette {
         println!(
             "\x1b[48;2;{};{};{}m   \x1b[0m RGB({:>3}, {:>3}, {:>3}). Freq: {}",
             weighted_color.color[0],
@@ -31,11 +58,212 @@ pub fn print_image_information(image_palette: Vec<WeightedColor>) {
         );
     }
 
-    println!("");
+    println!();
+}
+
+/// Palette swatches next to how they look with a color vision deficiency
+pub fn print_simulated_information(image_palette: &[WeightedColor], cvd: Cvd) {
+    for weighted_color in image_palette {
+        let [r, g, b] = weighted_color.color;
+        let [sr, sg, sb] = cvd.simulate(&weighted_color.color);
+        println!(
+            "\x1b[48;2;{};{};{}m   \x1b[0m\x1b[48;2;{};{};{}m   \x1b[0m RGB({:>3}, {:>3}, {:>3}) as RGB({:>3}, {:>3}, {:>3}). Freq: {}",
+            r, g, b, sr, sg, sb, r, g, b, sr, sg, sb, weighted_color.freq,
+        );
+    }
+
+    println!();
+}
+
+pub fn print_top_information(top: &[Match]) {
+    for m in top {
+        println!("{}\n{}\n", m.sprite, format_score(m.distance));
+    }
+}
+
+/// Similarity with the raw distance, e.g. `Similarity: 85.3% (distance 14.72)`
+pub fn format_score(distance: f32) -> String {
+    format!(
+        "Similarity: {:.1}% (distance {:.2})",
+        similarity(distance),
+        distance
+    )
+}
+
+/// Which colors of the image and each sprite were paired, and how much each
+/// pair adds to the distance
+pub fn print_explanations(image_palette: &[WeightedColor], top: &[Match]) {
+    for m in top {
+        println!("{}", format_result(m.sprite));
+        println!("  {}", format_score(m.distance));
+
+        println!("  Image -> sprite:");
+        let image_to_sprite = explain_pairs(&explain_distance(image_palette, &m.sprite.palette));
+        println!("  Sprite -> image:");
+        let sprite_to_image = explain_pairs(&explain_distance(&m.sprite.palette, image_palette));
+
+        println!(
+            "  Color distance: ({:.2} + {:.2}) / 2 = {:.2}\n",
+            image_to_sprite,
+            sprite_to_image,
+            (image_to_sprite + sprite_to_image) / 2.0
+        );
+    }
+}
+
+fn explain_pairs(pairs: &[ColorPair]) -> f32 {
+    for pair in pairs {
+        println!(
+            "    {} -> {} \u{394}E {:>6.2} x {:.3} = {:>6.2}",
+            swatch(&pair.from),
+            swatch(&pair.to),
+            pair.delta_e,
+            pair.weight,
+            pair.contribution()
+        );
+    }
+    pairs.iter().map(ColorPair::contribution).sum()
 }
 
-pub fn print_top_information(top: &Vec<(&Sprite, f32)>) {
-    for (sprite, distance) in top {
-        println!("{}\nScore: {}\n", sprite, distance);
+fn swatch(color: &[u8; 3]) -> String {
+    format!(
+        "\x1b[48;2;{};{};{}m   \x1b[0m RGB({:>3}, {:>3}, {:>3})",
+        color[0], color[1], color[2], color[0], color[1], color[2]
+    )
+}
+
+pub fn print_image_header(image: &str) {
+    println!("==> {} <==", image);
+}
+
+pub fn print_json_result(image: &str, top: &[Match]) {
+    println!("{}", json_result(image, top));
+}
+
+fn json_result(image: &str, top: &[Match]) -> serde_json::Value {
+    let matches: Vec<_> = top
+        .iter()
+        .map(|Match { sprite, distance }| {
+            json!({
+                "name": sprite.name,
+                "shiny": sprite.shiny,
+                "form": sprite.form.to_string(),
+                "female": sprite.gender == Gender::Female,
+                "distance": distance,
+                "similarity": similarity(*distance),
+                "krabby": format_result(sprite),
+            })
+        })
+        .collect();
+
+    json!({ "image": image, "matches": matches })
+}
+
+pub fn print_json_error(image: &str, error: &anyhow::Error) {
+    println!(
+        "{}",
+        json!({ "image": image, "error": format!("{:#}", error) })
+    );
+}
+
+pub fn print_csv_header() {
+    println!("image,rank,name,shiny,form,female,distance,similarity,krabby");
+}
+
+pub fn print_csv_result(image: &str, top: &[Match]) {
+    for row in csv_rows(image, top) {
+        println!("{}", row);
+    }
+}
+
+fn csv_rows(image: &str, top: &[Match]) -> Vec<String> {
+    top.iter()
+        .enumerate()
+        .map(|(i, Match { sprite, distance })| {
+            format!(
+                "{},{},{},{},{},{},{},{},{}",
+                csv_field(image),
+                i + 1,
+                csv_field(&sprite.name),
+                sprite.shiny,
+                csv_field(&sprite.form.to_string()),
+                sprite.gender == Gender::Female,
+                distance,
+                similarity(*distance),
+                csv_field(&format_result(sprite)),
+            )
+        })
+        .collect()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::MegaType;
+
+    #[test]
+    fn test_format_result_krabby_flags() {
+        let pikachu = Sprite::named("pikachu");
+        assert_eq!(format_result(&pikachu), "pikachu");
+
+        let female = Sprite::named("pikachu").shiny(true).female();
+        assert_eq!(format_result(&female), "pikachu -s -f female");
+
+        let mega = Sprite::named("charizard").form(Form::Mega(MegaType::X));
+        assert_eq!(format_result(&mega), "charizard -f mega-x");
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("walls/sunset.png"), "walls/sunset.png");
+        assert_eq!(csv_field("a,b.png"), "\"a,b.png\"");
+        assert_eq!(csv_field("say \"hi\".png"), "\"say \"\"hi\"\".png\"");
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let charizard = Sprite::named("charizard")
+            .shiny(true)
+            .form(Form::Mega(MegaType::X));
+        let top = [Match {
+            sprite: &charizard,
+            distance: 25.0,
+        }];
+        assert_eq!(
+            csv_rows("a,b.png", &top),
+            ["\"a,b.png\",1,charizard,true,mega-x,false,25,75,charizard -s -f mega-x"]
+        );
+    }
+
+    #[test]
+    fn test_json_result_shape() {
+        let pikachu = Sprite::named("pikachu").female();
+        let top = [Match {
+            sprite: &pikachu,
+            distance: 10.0,
+        }];
+        assert_eq!(
+            json_result("wall.png", &top),
+            json!({
+                "image": "wall.png",
+                "matches": [{
+                    "name": "pikachu",
+                    "shiny": false,
+                    "form": "regular",
+                    "female": true,
+                    "distance": 10.0,
+                    "similarity": 90.0,
+                    "krabby": "pikachu -f female",
+                }],
+            })
+        );
     }
 }
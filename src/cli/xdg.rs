@@ -0,0 +1,21 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Directory of pokepalette's files under an XDG base directory, e.g.
+/// `$XDG_CONFIG_HOME/pokepalette` falling back to `~/.config/pokepalette`
+fn app_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    let base = env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))?;
+
+    Some(base.join(env!("CARGO_PKG_NAME")))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    app_dir("XDG_CACHE_HOME", ".cache")
+}
@@ -0,0 +1,170 @@
+use super::color::{ColorSpace, Lab};
+use super::quantize::WeightedColor;
+
+/// Chroma at which a color counts as fully saturated
+const CHROMA_SCALE: f32 = 100.0;
+
+/// Colors with at least this chroma are accents, the rest are base colors
+const ACCENT_MIN_CHROMA: f32 = 25.0;
+
+/// Share of the distance given to accent colors when they are scored apart
+const ACCENT_SHARE: f32 = 0.5;
+
+/// How much saturated colors are favoured over greys. A color's weight is
+/// multiplied by `1 + strength * (chroma / 100) ^ exponent`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccentCurve {
+    pub strength: f32,
+    pub exponent: f32,
+}
+
+impl Default for AccentCurve {
+    fn default() -> Self {
+        Self {
+            strength: 0.0,
+            exponent: 1.0,
+        }
+    }
+}
+
+impl AccentCurve {
+    pub fn weight(&self, color: &[u8; 3]) -> f32 {
+        let saturation = (chroma(color) / CHROMA_SCALE).min(1.0);
+        1.0 + self.strength * saturation.powf(self.exponent)
+    }
+}
+
+/// Lab chroma of a color, 0.0 for greys
+pub fn chroma(color: &[u8; 3]) -> f32 {
+    Lab::from_rgb(*color).chroma()
+}
+
+/// Reweight a palette by the chroma of its colors, keeping the total weight
+pub fn accent_weighted(palette: &[WeightedColor], curve: AccentCurve) -> Vec<WeightedColor> {
+    let total: f32 = palette.iter().map(|wc| wc.freq).sum();
+    let weighted: Vec<WeightedColor> = palette
+        .iter()
+        .map(|wc| WeightedColor {
+            color: wc.color,
+            freq: wc.freq * curve.weight(&wc.color),
+        })
+        .collect();
+
+    normalized(weighted, total)
+}
+
+/// Split a palette into base and accent colors, each normalized to the total
+/// weight of the palette
+pub fn split_palette(palette: &[WeightedColor]) -> (Vec<WeightedColor>, Vec<WeightedColor>) {
+    let total: f32 = palette.iter().map(|wc| wc.freq).sum();
+    let (accent, base): (Vec<_>, Vec<_>) = palette
+        .iter()
+        .map(|wc| WeightedColor {
+            color: wc.color,
+            freq: wc.freq,
+        })
+        .partition(|wc| chroma(&wc.color) >= ACCENT_MIN_CHROMA);
+
+    (normalized(base, total), normalized(accent, total))
+}
+
+/// Wrap a metric so both palettes are reweighted by chroma first
+pub fn with_accent_weights(
+    metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32,
+    curve: AccentCurve,
+) -> impl Fn(&[WeightedColor], &[WeightedColor]) -> f32 {
+    move |a, b| metric(&accent_weighted(a, curve), &accent_weighted(b, curve))
+}
+
+/// Wrap a metric so base and accent colors are scored separately. When only
+/// one palette has colors of a kind, they are scored against the whole other
+/// palette.
+pub fn with_split_accents(
+    metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32,
+) -> impl Fn(&[WeightedColor], &[WeightedColor]) -> f32 {
+    move |a, b| {
+        let (base_a, accent_a) = split_palette(a);
+        let (base_b, accent_b) = split_palette(b);
+
+        let part = |part_a: &[WeightedColor], part_b: &[WeightedColor]| match (
+            part_a.is_empty(),
+            part_b.is_empty(),
+        ) {
+            (true, true) => None,
+            (false, true) => Some(metric(part_a, b)),
+            (true, false) => Some(metric(a, part_b)),
+            (false, false) => Some(metric(part_a, part_b)),
+        };
+
+        match (part(&base_a, &base_b), part(&accent_a, &accent_b)) {
+            (Some(base), Some(accent)) => (1.0 - ACCENT_SHARE) * base + ACCENT_SHARE * accent,
+            (Some(distance), None) | (None, Some(distance)) => distance,
+            (None, None) => metric(a, b),
+        }
+    }
+}
+
+fn normalized(palette: Vec<WeightedColor>, total: f32) -> Vec<WeightedColor> {
+    let sum: f32 = palette.iter().map(|wc| wc.freq).sum();
+    if sum <= 0.0 {
+        return palette;
+    }
+
+    palette
+        .into_iter()
+        .map(|wc| WeightedColor {
+            color: wc.color,
+            freq: wc.freq * total / sum,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colorquant::palette_distance;
+
+    fn wc(color: [u8; 3], freq: f32) -> WeightedColor {
+        WeightedColor { color, freq }
+    }
+
+    // Dark grey wallpaper with a small orange accent
+    fn wallpaper() -> Vec<WeightedColor> {
+        vec![wc([40, 40, 40], 0.9), wc([255, 120, 0], 0.1)]
+    }
+
+    #[test]
+    fn test_accent_weighted_favours_chroma() {
+        let curve = AccentCurve {
+            strength: 10.0,
+            exponent: 1.0,
+        };
+        let weighted = accent_weighted(&wallpaper(), curve);
+        assert!(weighted[1].freq > 0.4);
+        assert!((weighted[0].freq + weighted[1].freq - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_accent_weighted_disabled_keeps_palette() {
+        let weighted = accent_weighted(&wallpaper(), AccentCurve::default());
+        assert_eq!(weighted[0].freq, 0.9);
+        assert_eq!(weighted[1].freq, 0.1);
+    }
+
+    #[test]
+    fn test_split_palette() {
+        let (base, accent) = split_palette(&wallpaper());
+        assert_eq!(base.len(), 1);
+        assert_eq!(accent[0].color, [255, 120, 0]);
+        assert!((accent[0].freq - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_split_accents_rewards_matching_accent() {
+        let grey = vec![wc([60, 60, 60], 1.0)];
+        let orange = vec![wc([60, 60, 60], 0.7), wc([250, 110, 10], 0.3)];
+
+        let metric = with_split_accents(palette_distance);
+        assert!(metric(&wallpaper(), &orange) < metric(&wallpaper(), &grey));
+    }
+}
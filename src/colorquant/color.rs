@@ -0,0 +1,362 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// A color space palettes are converted to for comparison, they are
+/// quantized and stored as 8-bit sRGB.
+/// Every space converts exactly to and from linear RGB, so any two spaces
+/// convert through it without rounding to 8 bits.
+pub trait ColorSpace: Copy + From<LinearRgb> + Into<LinearRgb> {
+    /// Factor bringing euclidean distances to the range of CIELAB
+    const DISTANCE_SCALE: f32;
+
+    fn components(&self) -> [f32; 3];
+
+    fn from_components(components: [f32; 3]) -> Self;
+
+    /// Lightness, chroma and hue (in radians) for spaces that have them
+    fn lch(&self) -> Option<[f32; 3]> {
+        None
+    }
+
+    fn from_rgb(rgb: [u8; 3]) -> Self {
+        Self::from(LinearRgb::from(Srgb::from(rgb)))
+    }
+
+    /// 8-bit sRGB, clamped to the gamut
+    fn to_rgb(&self) -> [u8; 3] {
+        Srgb::from((*self).into()).to_rgb()
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        let (a, b) = (self.components(), other.components());
+        let squared: f32 = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum();
+        squared.sqrt() * Self::DISTANCE_SCALE
+    }
+}
+
+/// Color spaces selectable at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum Space {
+    Srgb,
+    Linear,
+    #[default]
+    Lab,
+    Oklab,
+}
+
+/// Gamma-encoded sRGB with components from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Srgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+/// sRGB primaries in linear light
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LinearRgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+/// CIELAB with a D65 white point
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Oklab, lightness goes from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+// D65 white point, matching the sRGB matrices below
+const WHITE_X: f32 = 0.950_449_2;
+const WHITE_Z: f32 = 1.088_916_6;
+
+const LINEAR_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412_410_85, 0.357_584_57, 0.180_453_8],
+    [0.212_649_34, 0.715_169_1, 0.072_181_52],
+    [0.019_331_76, 0.119_194_86, 0.950_390_04],
+];
+const XYZ_TO_LINEAR: [[f32; 3]; 3] = [
+    [3.240_812_4, -1.537_308_4, -0.498_586_5],
+    [-0.969_243, 1.875_966_3, 0.041_555_03],
+    [0.055_638_4, -0.204_007_46, 1.057_129_6],
+];
+
+const EPSILON: f32 = 216.0 / 24389.0;
+const KAPPA: f32 = 24389.0 / 27.0;
+
+// Oklab matrices from Björn Ottosson's reference implementation
+const LINEAR_TO_LMS: [[f32; 3]; 3] = [
+    [0.412_221_46, 0.536_332_55, 0.051_445_995],
+    [0.211_903_5, 0.680_699_5, 0.107_396_96],
+    [0.088_302_46, 0.281_718_85, 0.629_978_7],
+];
+const LMS_TO_OKLAB: [[f32; 3]; 3] = [
+    [0.210_454_26, 0.793_617_8, -0.004_072_047],
+    [1.977_998_5, -2.428_592_2, 0.450_593_7],
+    [0.025_904_037, 0.782_771_77, -0.808_675_77],
+];
+const OKLAB_TO_LMS: [[f32; 3]; 3] = [
+    [1.0, 0.396_337_78, 0.215_803_76],
+    [1.0, -0.105_561_346, -0.063_854_17],
+    [1.0, -0.089_484_18, -1.291_485_5],
+];
+const LMS_TO_LINEAR: [[f32; 3]; 3] = [
+    [4.076_741_7, -3.307_711_6, 0.230_969_94],
+    [-1.268_438, 2.609_757_4, -0.341_319_38],
+    [-0.004_196_086_3, -0.703_418_6, 1.707_614_7],
+];
+
+fn multiply(matrix: &[[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+fn polar(lightness: f32, a: f32, b: f32) -> [f32; 3] {
+    [lightness, a.hypot(b), b.atan2(a)]
+}
+
+impl From<[u8; 3]> for Srgb {
+    fn from(rgb: [u8; 3]) -> Self {
+        let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+        Self { r, g, b }
+    }
+}
+
+impl From<LinearRgb> for Srgb {
+    fn from(linear: LinearRgb) -> Self {
+        let encode = |c: f32| {
+            if c <= 0.003_130_8 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        let [r, g, b] = linear.components().map(encode);
+        Self { r, g, b }
+    }
+}
+
+impl From<Srgb> for LinearRgb {
+    fn from(srgb: Srgb) -> Self {
+        let decode = |c: f32| {
+            if c <= 0.040_45 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [r, g, b] = srgb.components().map(decode);
+        Self { r, g, b }
+    }
+}
+
+impl From<LinearRgb> for Lab {
+    fn from(linear: LinearRgb) -> Self {
+        let [x, y, z] = multiply(&LINEAR_TO_XYZ, linear.components());
+        let f = |c: f32| {
+            if c > EPSILON {
+                c.cbrt()
+            } else {
+                (KAPPA * c + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x / WHITE_X), f(y), f(z / WHITE_Z));
+
+        Self {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+}
+
+impl From<Lab> for LinearRgb {
+    fn from(lab: Lab) -> Self {
+        let fy = (lab.l + 16.0) / 116.0;
+        let fx = fy + lab.a / 500.0;
+        let fz = fy - lab.b / 200.0;
+        let f_inv = |f: f32| {
+            if f.powi(3) > EPSILON {
+                f.powi(3)
+            } else {
+                (116.0 * f - 16.0) / KAPPA
+            }
+        };
+        let y = if lab.l > EPSILON * KAPPA {
+            fy.powi(3)
+        } else {
+            lab.l / KAPPA
+        };
+        let xyz = [f_inv(fx) * WHITE_X, y, f_inv(fz) * WHITE_Z];
+
+        Self::from_components(multiply(&XYZ_TO_LINEAR, xyz))
+    }
+}
+
+impl From<LinearRgb> for Oklab {
+    fn from(linear: LinearRgb) -> Self {
+        let lms = multiply(&LINEAR_TO_LMS, linear.components()).map(f32::cbrt);
+        Self::from_components(multiply(&LMS_TO_OKLAB, lms))
+    }
+}
+
+impl From<Oklab> for LinearRgb {
+    fn from(oklab: Oklab) -> Self {
+        let lms = multiply(&OKLAB_TO_LMS, oklab.components()).map(|c| c.powi(3));
+        Self::from_components(multiply(&LMS_TO_LINEAR, lms))
+    }
+}
+
+impl Lab {
+    /// Color from lightness, chroma and hue in radians
+    pub fn from_lch(l: f32, c: f32, h: f32) -> Self {
+        Self {
+            l,
+            a: c * h.cos(),
+            b: c * h.sin(),
+        }
+    }
+
+    pub fn chroma(&self) -> f32 {
+        self.a.hypot(self.b)
+    }
+}
+
+impl ColorSpace for Srgb {
+    const DISTANCE_SCALE: f32 = 100.0;
+
+    fn components(&self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    fn from_components([r, g, b]: [f32; 3]) -> Self {
+        Self { r, g, b }
+    }
+
+    fn to_rgb(&self) -> [u8; 3] {
+        self.components()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
+
+impl ColorSpace for LinearRgb {
+    const DISTANCE_SCALE: f32 = 100.0;
+
+    fn components(&self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    fn from_components([r, g, b]: [f32; 3]) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl ColorSpace for Lab {
+    const DISTANCE_SCALE: f32 = 1.0;
+
+    fn components(&self) -> [f32; 3] {
+        [self.l, self.a, self.b]
+    }
+
+    fn from_components([l, a, b]: [f32; 3]) -> Self {
+        Self { l, a, b }
+    }
+
+    fn lch(&self) -> Option<[f32; 3]> {
+        Some(polar(self.l, self.a, self.b))
+    }
+}
+
+impl ColorSpace for Oklab {
+    const DISTANCE_SCALE: f32 = 100.0;
+
+    fn components(&self) -> [f32; 3] {
+        [self.l, self.a, self.b]
+    }
+
+    fn from_components([l, a, b]: [f32; 3]) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Scaled like the distance, so lightness goes from 0 to 100
+    fn lch(&self) -> Option<[f32; 3]> {
+        let scale = Self::DISTANCE_SCALE;
+        Some(polar(self.l * scale, self.a * scale, self.b * scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < tolerance, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_lab_reference_values() {
+        // Same values as the `lab` crate and colormine.org
+        assert_close(
+            Lab::from_rgb([255, 0, 0]).components(),
+            [53.2408, 80.0925, 67.2032],
+            0.01,
+        );
+        assert_close(
+            Lab::from_rgb([255, 255, 255]).components(),
+            [100.0, 0.0, 0.0],
+            0.01,
+        );
+    }
+
+    #[test]
+    fn test_oklab_reference_values() {
+        // From Björn Ottosson's Oklab post
+        assert_close(
+            Oklab::from_rgb([255, 255, 255]).components(),
+            [1.0, 0.0, 0.0],
+            1e-3,
+        );
+        assert_close(
+            Oklab::from_rgb([0, 0, 255]).components(),
+            [0.452, -0.0325, -0.3115],
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn test_round_trips_are_exact() {
+        for rgb in [[0, 64, 128], [255, 0, 0], [12, 200, 90], [0, 0, 0]] {
+            assert_eq!(Lab::from_rgb(rgb).to_rgb(), rgb);
+            assert_eq!(Oklab::from_rgb(rgb).to_rgb(), rgb);
+            assert_eq!(LinearRgb::from_rgb(rgb).to_rgb(), rgb);
+        }
+
+        let lab = Lab::from_rgb([12, 200, 90]);
+        let oklab = Oklab::from(LinearRgb::from(lab));
+        assert_close(
+            Lab::from(LinearRgb::from(oklab)).components(),
+            lab.components(),
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn test_distance_scales_are_comparable() {
+        let (black, white) = ([0, 0, 0], [255, 255, 255]);
+        let lab = Lab::from_rgb(black).distance(&Lab::from_rgb(white));
+        let oklab = Oklab::from_rgb(black).distance(&Oklab::from_rgb(white));
+        assert!((lab - 100.0).abs() < 0.1);
+        assert!((oklab - 100.0).abs() < 0.1);
+    }
+}
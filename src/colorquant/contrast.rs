@@ -0,0 +1,134 @@
+use super::color::{ColorSpace, LinearRgb};
+use super::quantize::WeightedColor;
+use std::str::FromStr;
+
+/// WCAG contrast ratio graphics need against their background
+pub const MIN_CONTRAST: f32 = 3.0;
+
+/// Distance added when none of a palette stands out from the background
+const CONTRAST_PENALTY: f32 = 50.0;
+
+/// Background sprites are shown on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    Color([u8; 3]),
+    /// The dominant color of the image
+    Auto,
+}
+
+impl Background {
+    /// Background color for an image palette, `None` for an empty palette
+    /// with an automatic background
+    pub fn resolve(&self, palette: &[WeightedColor]) -> Option<[u8; 3]> {
+        match self {
+            Background::Color(color) => Some(*color),
+            Background::Auto => palette
+                .iter()
+                .max_by(|a, b| a.freq.total_cmp(&b.freq))
+                .map(|wc| wc.color),
+        }
+    }
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    /// Parse "auto-from-image" or a hex color such as "#1e1e2e"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto-from-image" {
+            return Ok(Background::Auto);
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let invalid =
+            || format!("Invalid background: {s} (must be a hex color or auto-from-image)");
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut color = [0; 3];
+        for (i, channel) in color.iter_mut().enumerate() {
+            *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Background::Color(color))
+    }
+}
+
+/// WCAG relative luminance of an sRGB color
+pub fn relative_luminance(color: &[u8; 3]) -> f32 {
+    let linear = LinearRgb::from_rgb(*color);
+    0.2126 * linear.r + 0.7152 * linear.g + 0.0722 * linear.b
+}
+
+/// WCAG contrast ratio between two colors, from 1.0 to 21.0
+pub fn contrast_ratio(color1: &[u8; 3], color2: &[u8; 3]) -> f32 {
+    let l1 = relative_luminance(color1);
+    let l2 = relative_luminance(color2);
+    (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+}
+
+/// Penalty for the share of a palette that lacks contrast with the background
+pub fn contrast_penalty(palette: &[WeightedColor], background: &[u8; 3]) -> f32 {
+    let total: f32 = palette.iter().map(|wc| wc.freq).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+
+    let low_contrast: f32 = palette
+        .iter()
+        .filter(|wc| contrast_ratio(&wc.color, background) < MIN_CONTRAST)
+        .map(|wc| wc.freq)
+        .sum();
+
+    CONTRAST_PENALTY * low_contrast / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contrast_ratio_range() {
+        assert!((contrast_ratio(&[0, 0, 0], &[255, 255, 255]) - 21.0).abs() < 0.01);
+        assert_eq!(contrast_ratio(&[30, 30, 46], &[30, 30, 46]), 1.0);
+    }
+
+    #[test]
+    fn test_background_from_str() {
+        assert_eq!("#1e1e2e".parse(), Ok(Background::Color([30, 30, 46])));
+        assert_eq!("FFFFFF".parse(), Ok(Background::Color([255, 255, 255])));
+        assert_eq!("auto-from-image".parse(), Ok(Background::Auto));
+        assert!("#12345".parse::<Background>().is_err());
+        assert!("#gggggg".parse::<Background>().is_err());
+    }
+
+    #[test]
+    fn test_contrast_penalty() {
+        let background = [30, 30, 46];
+        let dark = vec![WeightedColor {
+            color: [40, 40, 60],
+            freq: 1.0,
+        }];
+        let bright = vec![WeightedColor {
+            color: [250, 220, 80],
+            freq: 1.0,
+        }];
+        assert_eq!(contrast_penalty(&dark, &background), CONTRAST_PENALTY);
+        assert_eq!(contrast_penalty(&bright, &background), 0.0);
+    }
+
+    #[test]
+    fn test_auto_background_is_dominant_color() {
+        let palette = vec![
+            WeightedColor {
+                color: [10, 10, 10],
+                freq: 0.3,
+            },
+            WeightedColor {
+                color: [200, 200, 200],
+                freq: 0.7,
+            },
+        ];
+        assert_eq!(Background::Auto.resolve(&palette), Some([200, 200, 200]));
+    }
+}
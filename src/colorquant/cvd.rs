@@ -0,0 +1,130 @@
+use super::color::{ColorSpace, LinearRgb};
+use super::quantize::WeightedColor;
+use clap::ValueEnum;
+
+/// Kind of color vision deficiency
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Deficiency {
+    /// Missing or weak red cones
+    Protan,
+    /// Missing or weak green cones
+    Deutan,
+    /// Missing or weak blue cones
+    Tritan,
+}
+
+/// Simulated color vision deficiency, `severity` goes from 0.0 (normal
+/// vision) to 1.0 (dichromacy)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cvd {
+    pub deficiency: Deficiency,
+    pub severity: f32,
+}
+
+// Machado, Oliveira and Fernandes (2009) matrices for full severity, applied
+// to linear RGB
+const PROTAN: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const DEUTAN: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+const TRITAN: [[f32; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+
+impl Cvd {
+    /// Simulation matrix, interpolated from normal vision for lower severities
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        let full = match self.deficiency {
+            Deficiency::Protan => PROTAN,
+            Deficiency::Deutan => DEUTAN,
+            Deficiency::Tritan => TRITAN,
+        };
+        let severity = self.severity.clamp(0.0, 1.0);
+
+        let mut matrix = [[0.0; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                let identity = if i == j { 1.0 } else { 0.0 };
+                *value = identity + severity * (full[i][j] - identity);
+            }
+        }
+        matrix
+    }
+
+    /// How a color looks with this deficiency
+    pub fn simulate(&self, color: &[u8; 3]) -> [u8; 3] {
+        let linear = LinearRgb::from_rgb(*color).components();
+        let simulated = self
+            .matrix()
+            .map(|row| row.iter().zip(linear).map(|(m, c)| m * c).sum());
+
+        LinearRgb::from_components(simulated).to_rgb()
+    }
+
+    pub fn simulate_palette(&self, palette: &[WeightedColor]) -> Vec<WeightedColor> {
+        palette
+            .iter()
+            .map(|wc| WeightedColor {
+                color: self.simulate(&wc.color),
+                freq: wc.freq,
+            })
+            .collect()
+    }
+}
+
+/// Wrap a metric so both palettes are compared as seen with a deficiency
+pub fn with_cvd(
+    metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32,
+    cvd: Cvd,
+) -> impl Fn(&[WeightedColor], &[WeightedColor]) -> f32 {
+    move |a, b| metric(&cvd.simulate_palette(a), &cvd.simulate_palette(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cvd(deficiency: Deficiency, severity: f32) -> Cvd {
+        Cvd {
+            deficiency,
+            severity,
+        }
+    }
+
+    #[test]
+    fn test_no_severity_keeps_colors() {
+        let color = [200, 60, 30];
+        assert_eq!(cvd(Deficiency::Deutan, 0.0).simulate(&color), color);
+    }
+
+    #[test]
+    fn test_greys_are_unchanged() {
+        for deficiency in [Deficiency::Protan, Deficiency::Deutan, Deficiency::Tritan] {
+            let grey = cvd(deficiency, 1.0).simulate(&[128, 128, 128]);
+            assert!(grey.iter().all(|c| c.abs_diff(128) <= 2), "{grey:?}");
+        }
+    }
+
+    #[test]
+    fn test_deutan_confuses_red_and_green() {
+        let deutan = cvd(Deficiency::Deutan, 1.0);
+        let (red, green) = ([200, 80, 40], [110, 130, 40]);
+        let distance = |a: [u8; 3], b: [u8; 3]| {
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| (*x as f32 - y as f32).powi(2))
+                .sum::<f32>()
+        };
+        assert!(
+            distance(deutan.simulate(&red), deutan.simulate(&green)) < distance(red, green) / 4.0
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use anyhow::{anyhow, Result};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, DynamicImage, ImageFormat, ImageReader, RgbaImage};
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+// Browsers show frames without a delay for about 100ms
+const DEFAULT_FRAME_DELAY_MS: f32 = 100.0;
+
+/// Frames of an animated image that feed the palette
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Frames {
+    #[default]
+    First,
+    All,
+    /// The first N frames
+    Count(usize),
+}
+
+/// A decoded frame, weighted by how long it is shown
+pub struct WeightedFrame {
+    pub image: RgbaImage,
+    pub weight: f32,
+}
+
+/// Open an image file, detecting the format from its content and falling
+/// back to the extension for formats without a signature (e.g. TGA)
+pub fn open_image(path: impl AsRef<Path>) -> Result<DynamicImage> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)?;
+    decode_image(&bytes, ImageFormat::from_path(path).ok())
+}
+
+/// Read a whole image from a reader such as stdin
+pub fn read_image(mut reader: impl Read) -> Result<DynamicImage> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    decode_image(&bytes, None)
+}
+
+pub fn decode_image(bytes: &[u8], hint: Option<ImageFormat>) -> Result<DynamicImage> {
+    let format = detect_format(bytes, hint)?;
+
+    ImageReader::with_format(Cursor::new(bytes), format)
+        .decode()
+        .map_err(|err| decode_error(format, err))
+}
+
+/// Open an image file as frames, see `decode_frames`
+pub fn open_frames(path: impl AsRef<Path>, frames: Frames) -> Result<Vec<WeightedFrame>> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)?;
+    decode_frames(&bytes, ImageFormat::from_path(path).ok(), frames)
+}
+
+/// Read a whole image from a reader as frames, see `decode_frames`
+pub fn read_frames(mut reader: impl Read, frames: Frames) -> Result<Vec<WeightedFrame>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    decode_frames(&bytes, None, frames)
+}
+
+/// Decode the frames of an animated GIF, APNG or WebP. Still images, and
+/// any image with `Frames::First`, decode to a single frame.
+pub fn decode_frames(
+    bytes: &[u8],
+    hint: Option<ImageFormat>,
+    frames: Frames,
+) -> Result<Vec<WeightedFrame>> {
+    let format = detect_format(bytes, hint)?;
+    let limit = match frames {
+        Frames::First => usize::MIN,
+        Frames::All => usize::MAX,
+        Frames::Count(count) => count,
+    };
+
+    let animation = if limit > 1 {
+        animation_frames(bytes, format).map_err(|err| decode_error(format, err))?
+    } else {
+        None
+    };
+
+    let Some(animation) = animation else {
+        let image = decode_image(bytes, Some(format))?;
+        return Ok(vec![WeightedFrame::from(&image)]);
+    };
+
+    animation
+        .take(limit)
+        .map(|frame| {
+            let frame = frame.map_err(|err| decode_error(format, err))?;
+            Ok(WeightedFrame {
+                weight: frame_weight(frame.delay()),
+                image: frame.into_buffer(),
+            })
+        })
+        .collect()
+}
+
+fn animation_frames(
+    bytes: &[u8],
+    format: ImageFormat,
+) -> image::ImageResult<Option<image::Frames<'_>>> {
+    let cursor = Cursor::new(bytes);
+    let frames = match format {
+        ImageFormat::Gif => Some(GifDecoder::new(cursor)?.into_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor)?;
+            if decoder.is_apng()? {
+                Some(decoder.apng()?.into_frames())
+            } else {
+                None
+            }
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(cursor)?;
+            if decoder.has_animation() {
+                Some(decoder.into_frames())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    Ok(frames)
+}
+
+fn frame_weight(delay: Delay) -> f32 {
+    let (numer, denom) = delay.numer_denom_ms();
+    let delay_ms = numer as f32 / denom.max(1) as f32;
+    if delay_ms > 0.0 {
+        delay_ms
+    } else {
+        DEFAULT_FRAME_DELAY_MS
+    }
+}
+
+fn detect_format(bytes: &[u8], hint: Option<ImageFormat>) -> Result<ImageFormat> {
+    if bytes.is_empty() {
+        return Err(anyhow!("Image is empty"));
+    }
+
+    let format = image::guess_format(bytes)
+        .ok()
+        .or(hint)
+        .ok_or_else(|| anyhow!("Unrecognized image format"))?;
+
+    if !decoding_enabled(format) {
+        return Err(anyhow!("{} images are not supported", format_name(format)));
+    }
+
+    Ok(format)
+}
+
+fn decode_error(format: ImageFormat, err: image::ImageError) -> anyhow::Error {
+    anyhow!("Failed to decode {} image: {}", format_name(format), err)
+}
+
+fn decoding_enabled(format: ImageFormat) -> bool {
+    match format {
+        // image only decodes AVIF through its `avif-native` feature, which
+        // links against the system dav1d library and is opt-in
+        ImageFormat::Avif => cfg!(feature = "avif"),
+        format => format.reading_enabled(),
+    }
+}
+
+fn format_name(format: ImageFormat) -> String {
+    format
+        .extensions_str()
+        .first()
+        .map_or_else(|| format!("{:?}", format), |ext| ext.to_uppercase())
+}
+
+impl From<&DynamicImage> for WeightedFrame {
+    fn from(image: &DynamicImage) -> Self {
+        Self {
+            image: image.to_rgba8(),
+            weight: 1.0,
+        }
+    }
+}
+
+impl FromStr for Frames {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Frames::First),
+            "all" => Ok(Frames::All),
+            count => match count.parse() {
+                Ok(0) | Err(_) => Err(format!(
+                    "Invalid frames: {count} (must be first, all or a positive number)"
+                )),
+                Ok(count) => Ok(Frames::Count(count)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Frames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Frames::First => write!(f, "first"),
+            Frames::All => write!(f, "all"),
+            Frames::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_bytes() -> Vec<u8> {
+        let image =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0])));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_decode_image_sniffs_format() {
+        let image = decode_image(&png_bytes(), None).unwrap();
+        assert_eq!(image.to_rgb8().get_pixel(0, 0).0, [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_read_image_from_reader() {
+        let image = read_image(Cursor::new(png_bytes())).unwrap();
+        assert_eq!(image.width(), 2);
+    }
+
+    #[test]
+    fn test_decode_image_unrecognized() {
+        let error = decode_image(b"not an image", None).unwrap_err();
+        assert_eq!(error.to_string(), "Unrecognized image format");
+    }
+
+    #[test]
+    fn test_decode_image_names_broken_format() {
+        let mut bytes = png_bytes();
+        bytes.truncate(20);
+        let error = decode_image(&bytes, None).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to decode PNG image"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "avif"))]
+    fn test_decode_image_names_unsupported_format() {
+        let error = decode_image(b"\0\0\0\x1cftypavif\0\0\0\0", None).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("AVIF images are not supported"));
+    }
+
+    fn gif_bytes(colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes);
+            for color in colors {
+                let frame = image::Frame::from_parts(
+                    RgbaImage::from_pixel(2, 2, image::Rgba(*color)),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(50, 1),
+                );
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_frames_from_str() {
+        assert_eq!("first".parse(), Ok(Frames::First));
+        assert_eq!("all".parse(), Ok(Frames::All));
+        assert_eq!("3".parse(), Ok(Frames::Count(3)));
+        assert!("0".parse::<Frames>().is_err());
+        assert!("some".parse::<Frames>().is_err());
+    }
+
+    #[test]
+    fn test_decode_frames_animation() {
+        let bytes = gif_bytes(&[[255, 0, 0, 255], [0, 0, 255, 255], [0, 255, 0, 255]]);
+        assert_eq!(decode_frames(&bytes, None, Frames::First).unwrap().len(), 1);
+        assert_eq!(
+            decode_frames(&bytes, None, Frames::Count(2)).unwrap().len(),
+            2
+        );
+
+        let frames = decode_frames(&bytes, None, Frames::All).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(frames[1].weight, 50.0);
+    }
+
+    #[test]
+    fn test_decode_frames_still_image() {
+        let frames = decode_frames(&png_bytes(), None, Frames::All).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].weight, 1.0);
+    }
+}
@@ -1,20 +1,161 @@
+use super::color::{ColorSpace, Lab, LinearRgb, Oklab, Space, Srgb};
 use super::quantize::WeightedColor;
-use lab::Lab;
+use clap::ValueEnum;
 
-pub fn palette_distance(palette_a: &Vec<WeightedColor>, palette_b: &Vec<WeightedColor>) -> f32 {
+/// Which parts of a color are compared when matching palettes
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum MatchMode {
+    /// Hue only, chroma barely counts and lightness is ignored
+    Hue,
+    /// Hue and chroma, lightness barely counts
+    ChromaHue,
+    /// Lightness, chroma and hue alike
+    #[default]
+    Full,
+}
+
+impl MatchMode {
+    /// Weights of the lightness, chroma and hue differences
+    fn weights(self) -> [f32; 3] {
+        match self {
+            MatchMode::Hue => [0.0, 0.25, 1.0],
+            MatchMode::ChromaHue => [0.25, 1.0, 1.0],
+            MatchMode::Full => [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Distance at which palettes count as 0% similar, the CIELAB distance
+/// between black and white
+const MAX_DISTANCE: f32 = 100.0;
+
+/// A color of one palette paired with the closest color of another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorPair {
+    pub from: [u8; 3],
+    pub to: [u8; 3],
+    /// CIELAB distance between the colors
+    pub delta_e: f32,
+    /// Share of the palette `from` belongs to
+    pub weight: f32,
+}
+
+impl ColorPair {
+    /// What the pair adds to the directional distance
+    pub fn contribution(&self) -> f32 {
+        self.delta_e * self.weight
+    }
+}
+
+/// Similarity from 0% to 100% for a palette distance
+pub fn similarity(distance: f32) -> f32 {
+    100.0 * (1.0 - distance / MAX_DISTANCE).clamp(0.0, 1.0)
+}
+
+/// Pairs behind the distance from one palette to another, as summed by
+/// `palette_distance` in each direction
+pub fn explain_distance(
+    from_palette: &[WeightedColor],
+    to_palette: &[WeightedColor],
+) -> Vec<ColorPair> {
+    from_palette
+        .iter()
+        .filter_map(|wc_from| {
+            to_palette
+                .iter()
+                .map(|wc_to| ColorPair {
+                    from: wc_from.color,
+                    to: wc_to.color,
+                    delta_e: euclidean_distance(&wc_from.color, &wc_to.color),
+                    weight: wc_from.freq,
+                })
+                .min_by(|a, b| {
+                    a.delta_e
+                        .partial_cmp(&b.delta_e)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+        })
+        .collect()
+}
+
+pub fn palette_distance(palette_a: &[WeightedColor], palette_b: &[WeightedColor]) -> f32 {
     let distance_a_to_b = directional_distance(palette_a, palette_b);
     let distance_b_to_a = directional_distance(palette_b, palette_a);
 
     (distance_a_to_b + distance_b_to_a) / 2.0
 }
 
-fn directional_distance(from_palette: &Vec<WeightedColor>, to_palette: &Vec<WeightedColor>) -> f32 {
+/// Same as `palette_distance`, comparing only the parts of colors picked by
+/// the match mode
+pub fn palette_distance_with(
+    palette_a: &[WeightedColor],
+    palette_b: &[WeightedColor],
+    mode: MatchMode,
+) -> f32 {
+    palette_distance_in(palette_a, palette_b, mode, Space::Lab)
+}
+
+/// Same as `palette_distance_with`, with colors compared in the given space.
+/// Spaces without hue (sRGB and linear RGB) fall back to CIELAB for the hue
+/// modes.
+pub fn palette_distance_in(
+    palette_a: &[WeightedColor],
+    palette_b: &[WeightedColor],
+    mode: MatchMode,
+    space: Space,
+) -> f32 {
+    match space {
+        Space::Srgb => converted_distance::<Srgb>(palette_a, palette_b, mode),
+        Space::Linear => converted_distance::<LinearRgb>(palette_a, palette_b, mode),
+        Space::Lab => converted_distance::<Lab>(palette_a, palette_b, mode),
+        Space::Oklab => converted_distance::<Oklab>(palette_a, palette_b, mode),
+    }
+}
+
+/// Distance between palettes that are already in a color space
+pub fn space_palette_distance<C: ColorSpace>(
+    palette_a: &[WeightedColor<C>],
+    palette_b: &[WeightedColor<C>],
+    mode: MatchMode,
+) -> f32 {
+    let distance_a_to_b = directional_distance_with(palette_a, palette_b, mode);
+    let distance_b_to_a = directional_distance_with(palette_b, palette_a, mode);
+
+    (distance_a_to_b + distance_b_to_a) / 2.0
+}
+
+fn converted_distance<C: ColorSpace>(
+    palette_a: &[WeightedColor],
+    palette_b: &[WeightedColor],
+    mode: MatchMode,
+) -> f32 {
+    // Convert once per palette rather than once per pair of colors
+    let palette_a: Vec<WeightedColor<C>> = palette_a.iter().map(WeightedColor::to_space).collect();
+    let palette_b: Vec<WeightedColor<C>> = palette_b.iter().map(WeightedColor::to_space).collect();
+
+    space_palette_distance(&palette_a, &palette_b, mode)
+}
+
+fn directional_distance(from_palette: &[WeightedColor], to_palette: &[WeightedColor]) -> f32 {
+    let from_palette: Vec<WeightedColor<Lab>> =
+        from_palette.iter().map(WeightedColor::to_space).collect();
+    let to_palette: Vec<WeightedColor<Lab>> =
+        to_palette.iter().map(WeightedColor::to_space).collect();
+
+    directional_distance_with(&from_palette, &to_palette, MatchMode::Full)
+}
+
+fn directional_distance_with<C: ColorSpace>(
+    from_palette: &[WeightedColor<C>],
+    to_palette: &[WeightedColor<C>],
+    mode: MatchMode,
+) -> f32 {
     let mut total_weighted_distance = 0.0;
 
     for wc_from in from_palette {
         let min_distance = to_palette
             .iter()
-            .map(|wc_to| euclidean_distance(&wc_from.color, &wc_to.color))
+            .map(|wc_to| space_distance(&wc_from.color, &wc_to.color, mode))
             .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
             .unwrap_or(f32::MAX);
 
@@ -24,20 +165,45 @@ fn directional_distance(from_palette: &Vec<WeightedColor>, to_palette: &Vec<Weig
     total_weighted_distance
 }
 
-fn euclidean_distance(color1: &[u8; 3], color2: &[u8; 3]) -> f32 {
+/// CIELAB distance between two sRGB colors
+pub fn euclidean_distance(color1: &[u8; 3], color2: &[u8; 3]) -> f32 {
     // Convert RGB to Lab for more accurate distance calculation
-    let lab1 = Lab::from_rgb(color1);
-    let lab2 = Lab::from_rgb(color2);
-    let dl = lab1.l - lab2.l;
-    let da = lab1.a - lab2.a;
-    let db = lab1.b - lab2.b;
-    (dl * dl + da * da + db * db).sqrt()
+    Lab::from_rgb(*color1).distance(&Lab::from_rgb(*color2))
+}
+
+fn space_distance<C: ColorSpace>(color1: &C, color2: &C, mode: MatchMode) -> f32 {
+    if mode == MatchMode::Full {
+        return color1.distance(color2);
+    }
+
+    let lch = |color: &C| {
+        color.lch().unwrap_or_else(|| {
+            Lab::from(Into::<LinearRgb>::into(*color))
+                .lch()
+                .unwrap_or_default()
+        })
+    };
+
+    // Distance in LCh, with the CIE hue difference so hue counts less for
+    // greyish colors
+    let [l1, c1, h1] = lch(color1);
+    let [l2, c2, h2] = lch(color2);
+    let dl = l1 - l2;
+    let dc = c1 - c2;
+    let dh = 2.0 * (c1 * c2).sqrt() * ((h1 - h2) / 2.0).sin();
+
+    let [wl, wc, wh] = mode.weights();
+    ((wl * dl).powi(2) + (wc * dc).powi(2) + (wh * dh).powi(2)).sqrt()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn color_distance(color1: &[u8; 3], color2: &[u8; 3], mode: MatchMode) -> f32 {
+        space_distance(&Lab::from_rgb(*color1), &Lab::from_rgb(*color2), mode)
+    }
+
     #[test]
     fn test_euclidean_distance_identical_colors() {
         let color = [128, 128, 128];
@@ -75,8 +241,14 @@ mod tests {
     #[test]
     fn test_directional_distance_identical_palettes() {
         let palette = vec![
-            WeightedColor { color: [255, 0, 0], freq: 0.5 },
-            WeightedColor { color: [0, 255, 0], freq: 0.5 },
+            WeightedColor {
+                color: [255, 0, 0],
+                freq: 0.5,
+            },
+            WeightedColor {
+                color: [0, 255, 0],
+                freq: 0.5,
+            },
         ];
         let distance = directional_distance(&palette, &palette);
         assert_eq!(distance, 0.0);
@@ -84,17 +256,20 @@ mod tests {
 
     #[test]
     fn test_directional_distance_weights_matter() {
-        let palette_a = vec![
-            WeightedColor { color: [255, 0, 0], freq: 1.0 },
-        ];
-        let palette_b = vec![
-            WeightedColor { color: [0, 0, 255], freq: 1.0 },
-        ];
+        let palette_a = vec![WeightedColor {
+            color: [255, 0, 0],
+            freq: 1.0,
+        }];
+        let palette_b = vec![WeightedColor {
+            color: [0, 0, 255],
+            freq: 1.0,
+        }];
         let d1 = directional_distance(&palette_a, &palette_b);
 
-        let palette_a_low_weight = vec![
-            WeightedColor { color: [255, 0, 0], freq: 0.1 },
-        ];
+        let palette_a_low_weight = vec![WeightedColor {
+            color: [255, 0, 0],
+            freq: 0.1,
+        }];
         let d2 = directional_distance(&palette_a_low_weight, &palette_b);
 
         // Lower weight should result in lower total distance
@@ -104,23 +279,134 @@ mod tests {
     #[test]
     fn test_palette_distance_symmetry() {
         let palette_a = vec![
-            WeightedColor { color: [255, 0, 0], freq: 0.7 },
-            WeightedColor { color: [0, 255, 0], freq: 0.3 },
+            WeightedColor {
+                color: [255, 0, 0],
+                freq: 0.7,
+            },
+            WeightedColor {
+                color: [0, 255, 0],
+                freq: 0.3,
+            },
         ];
         let palette_b = vec![
-            WeightedColor { color: [0, 0, 255], freq: 0.5 },
-            WeightedColor { color: [255, 255, 0], freq: 0.5 },
+            WeightedColor {
+                color: [0, 0, 255],
+                freq: 0.5,
+            },
+            WeightedColor {
+                color: [255, 255, 0],
+                freq: 0.5,
+            },
         ];
         let d1 = palette_distance(&palette_a, &palette_b);
         let d2 = palette_distance(&palette_b, &palette_a);
         assert_eq!(d1, d2);
     }
 
+    #[test]
+    fn test_color_distance_full_matches_euclidean() {
+        let (red, blue) = ([255, 0, 0], [0, 0, 255]);
+        let full = color_distance(&red, &blue, MatchMode::Full);
+        let lch = color_distance(&red, &blue, MatchMode::ChromaHue);
+        assert_eq!(full, euclidean_distance(&red, &blue));
+        assert!(lch < full);
+    }
+
+    #[test]
+    fn test_hue_mode_ignores_lightness() {
+        let dark_blue = vec![WeightedColor {
+            color: [20, 30, 90],
+            freq: 1.0,
+        }];
+        let light_blue = vec![WeightedColor {
+            color: [150, 180, 250],
+            freq: 1.0,
+        }];
+        let dark_red = vec![WeightedColor {
+            color: [90, 20, 30],
+            freq: 1.0,
+        }];
+
+        // By lightness the dark red is closer, by hue the light blue is
+        assert!(
+            palette_distance(&dark_blue, &dark_red) < palette_distance(&dark_blue, &light_blue)
+        );
+        assert!(
+            palette_distance_with(&dark_blue, &light_blue, MatchMode::Hue)
+                < palette_distance_with(&dark_blue, &dark_red, MatchMode::Hue)
+        );
+    }
+
+    #[test]
+    fn test_palette_distance_in_spaces() {
+        let palette_a = vec![WeightedColor {
+            color: [200, 40, 40],
+            freq: 1.0,
+        }];
+        let palette_b = vec![WeightedColor {
+            color: [40, 40, 200],
+            freq: 1.0,
+        }];
+        let lab = palette_distance_in(&palette_a, &palette_b, MatchMode::Full, Space::Lab);
+        assert_eq!(lab, palette_distance(&palette_a, &palette_b));
+        for space in [Space::Srgb, Space::Linear, Space::Oklab] {
+            let distance = palette_distance_in(&palette_a, &palette_b, MatchMode::Full, space);
+            assert!(distance > 10.0 && distance < 200.0, "{space:?}: {distance}");
+            assert_eq!(
+                palette_distance_in(&palette_a, &palette_a, MatchMode::Full, space),
+                0.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_distance_adds_up() {
+        let palette_a = vec![
+            WeightedColor {
+                color: [255, 0, 0],
+                freq: 0.7,
+            },
+            WeightedColor {
+                color: [0, 255, 0],
+                freq: 0.3,
+            },
+        ];
+        let palette_b = vec![
+            WeightedColor {
+                color: [250, 10, 10],
+                freq: 0.5,
+            },
+            WeightedColor {
+                color: [0, 0, 255],
+                freq: 0.5,
+            },
+        ];
+        let pairs = explain_distance(&palette_a, &palette_b);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].to, [250, 10, 10]);
+
+        let total: f32 = pairs.iter().map(ColorPair::contribution).sum();
+        assert!((total - directional_distance(&palette_a, &palette_b)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_similarity_range() {
+        assert_eq!(similarity(0.0), 100.0);
+        assert_eq!(similarity(25.0), 75.0);
+        assert_eq!(similarity(250.0), 0.0);
+    }
+
     #[test]
     fn test_palette_distance_identical() {
         let palette = vec![
-            WeightedColor { color: [128, 64, 32], freq: 0.6 },
-            WeightedColor { color: [200, 100, 50], freq: 0.4 },
+            WeightedColor {
+                color: [128, 64, 32],
+                freq: 0.6,
+            },
+            WeightedColor {
+                color: [200, 100, 50],
+                freq: 0.4,
+            },
         ];
         let distance = palette_distance(&palette, &palette);
         assert_eq!(distance, 0.0);
@@ -0,0 +1,231 @@
+use super::saliency::saliency_weights;
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, RgbaImage};
+use std::str::FromStr;
+
+/// Rectangle in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Halves of the image, `Center` is the middle half in both directions
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum RegionPreset {
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Crop(Crop),
+    Preset(RegionPreset),
+}
+
+/// Parts of an image that feed the palette. Pixels outside the region are
+/// dropped, the mask's alpha scales the weight of the pixels inside it and
+/// saliency favours the subject over the background.
+#[derive(Default, Clone)]
+pub struct Focus {
+    pub region: Option<Region>,
+    pub mask: Option<GrayImage>,
+    pub saliency: bool,
+}
+
+impl Focus {
+    /// Use the alpha channel of an image as mask, or its luminance if it has
+    /// no alpha channel
+    pub fn mask_from_image(image: &DynamicImage) -> GrayImage {
+        if image.color().has_alpha() {
+            let luma_alpha = image.to_luma_alpha8();
+            GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                image::Luma([luma_alpha.get_pixel(x, y)[1]])
+            })
+        } else {
+            image.to_luma8()
+        }
+    }
+
+    /// Weight of each pixel of `image` in row-major order, 0.0 for pixels
+    /// that are left out
+    pub fn weights(&self, image: &RgbaImage) -> Vec<f32> {
+        let (width, height) = image.dimensions();
+        let bounds = self.region.map_or(Crop::full(width, height), |region| {
+            region.bounds(width, height)
+        });
+
+        let mask = self.mask.as_ref().map(|mask| {
+            if mask.dimensions() == (width, height) {
+                mask.clone()
+            } else {
+                imageops::resize(mask, width, height, FilterType::Triangle)
+            }
+        });
+
+        let saliency = self.saliency.then(|| saliency_weights(image));
+
+        let mut weights = Vec::with_capacity((width * height) as usize);
+        for (i, (x, y, pixel)) in image.enumerate_pixels().enumerate() {
+            let weight = if pixel[3] == 0 || !bounds.contains(x, y) {
+                // Skip fully transparent pixels and pixels outside the region
+                0.0
+            } else {
+                let mask = mask
+                    .as_ref()
+                    .map_or(1.0, |mask| mask.get_pixel(x, y)[0] as f32 / 255.0);
+                mask * saliency.as_ref().map_or(1.0, |saliency| saliency[i])
+            };
+            weights.push(weight);
+        }
+
+        weights
+    }
+}
+
+impl Crop {
+    fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x - self.x < self.width && y >= self.y && y - self.y < self.height
+    }
+}
+
+impl Region {
+    /// Bounds of the region in an image of the given size
+    pub fn bounds(&self, width: u32, height: u32) -> Crop {
+        match self {
+            Region::Crop(crop) => *crop,
+            Region::Preset(RegionPreset::Center) => Crop {
+                x: width / 4,
+                y: height / 4,
+                width: width.div_ceil(2),
+                height: height.div_ceil(2),
+            },
+            Region::Preset(RegionPreset::Left) => Crop {
+                width: width.div_ceil(2),
+                ..Crop::full(width, height)
+            },
+            Region::Preset(RegionPreset::Right) => Crop {
+                x: width / 2,
+                width: width.div_ceil(2),
+                ..Crop::full(width, height)
+            },
+            Region::Preset(RegionPreset::Top) => Crop {
+                height: height.div_ceil(2),
+                ..Crop::full(width, height)
+            },
+            Region::Preset(RegionPreset::Bottom) => Crop {
+                y: height / 2,
+                height: height.div_ceil(2),
+                ..Crop::full(width, height)
+            },
+        }
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    /// Parse "x,y,w,h"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<u32> = s
+            .split(',')
+            .map(|part| part.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid crop: {s} (must be x,y,w,h)"))?;
+
+        let [x, y, width, height] = parts[..] else {
+            return Err(format!("Invalid crop: {s} (must be x,y,w,h)"));
+        };
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Invalid crop: {s} (width and height must be positive)"
+            ));
+        }
+
+        Ok(Crop {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn included(focus: &Focus, width: u32, height: u32) -> usize {
+        let image = RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]));
+        focus.weights(&image).iter().filter(|&&w| w > 0.0).count()
+    }
+
+    #[test]
+    fn test_crop_from_str() {
+        assert_eq!(
+            "1,2,30,40".parse(),
+            Ok(Crop {
+                x: 1,
+                y: 2,
+                width: 30,
+                height: 40
+            })
+        );
+        assert!("1,2,30".parse::<Crop>().is_err());
+        assert!("1,2,0,40".parse::<Crop>().is_err());
+    }
+
+    #[test]
+    fn test_focus_default_keeps_opaque_pixels() {
+        let mut image = RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(0, 0, image::Rgba([0, 0, 0, 0]));
+        assert_eq!(Focus::default().weights(&image), vec![0.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_focus_regions() {
+        let focus = |preset| Focus {
+            region: Some(Region::Preset(preset)),
+            ..Default::default()
+        };
+        assert_eq!(included(&focus(RegionPreset::Left), 4, 4), 8);
+        assert_eq!(included(&focus(RegionPreset::Bottom), 4, 4), 8);
+        assert_eq!(included(&focus(RegionPreset::Center), 4, 4), 4);
+    }
+
+    #[test]
+    fn test_focus_crop_outside_image() {
+        let focus = Focus {
+            region: Some(Region::Crop("2,2,10,10".parse().unwrap())),
+            ..Default::default()
+        };
+        assert_eq!(included(&focus, 4, 4), 4);
+    }
+
+    #[test]
+    fn test_focus_mask_scales_weights() {
+        let mut mask = GrayImage::from_pixel(2, 1, image::Luma([255]));
+        mask.put_pixel(1, 0, image::Luma([0]));
+        let focus = Focus {
+            mask: Some(mask),
+            ..Default::default()
+        };
+        let image = RgbaImage::from_pixel(2, 1, image::Rgba([255, 0, 0, 255]));
+        assert_eq!(focus.weights(&image), vec![1.0, 0.0]);
+    }
+}
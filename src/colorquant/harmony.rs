@@ -0,0 +1,91 @@
+use super::color::{ColorSpace, Lab};
+use super::quantize::WeightedColor;
+use clap::ValueEnum;
+
+/// Color schemes to match instead of similar colors
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Harmony {
+    /// Opposite hue
+    Complementary,
+    /// Neighbouring hues, 30° to each side
+    Analogous,
+    /// Hues a third of the wheel apart
+    Triadic,
+    /// The two hues next to the opposite one
+    SplitComplementary,
+}
+
+impl Harmony {
+    /// Hue rotations of the scheme in degrees
+    pub fn rotations(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[180.0],
+            Harmony::Analogous => &[-30.0, 30.0],
+            Harmony::Triadic => &[120.0, 240.0],
+            Harmony::SplitComplementary => &[150.0, 210.0],
+        }
+    }
+}
+
+/// Palette of the colors that harmonize with `palette`. Each color is rotated
+/// in LCh, keeping its lightness and chroma, and its weight is shared between
+/// the rotations.
+pub fn harmony_palette(palette: &[WeightedColor], harmony: Harmony) -> Vec<WeightedColor> {
+    let rotations = harmony.rotations();
+
+    palette
+        .iter()
+        .flat_map(|wc| {
+            let lab = Lab::from_rgb(wc.color);
+            let (chroma, hue) = (lab.chroma(), lab.b.atan2(lab.a));
+            rotations.iter().map(move |degrees| WeightedColor {
+                color: Lab::from_lch(lab.l, chroma, hue + degrees.to_radians()).to_rgb(),
+                freq: wc.freq / rotations.len() as f32,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hue(color: &[u8; 3]) -> f32 {
+        let lab = Lab::from_rgb(*color);
+        lab.b.atan2(lab.a).to_degrees()
+    }
+
+    #[test]
+    fn test_complementary_opposite_hue() {
+        let palette = vec![WeightedColor {
+            color: [170, 120, 110],
+            freq: 1.0,
+        }];
+        let complementary = harmony_palette(&palette, Harmony::Complementary);
+        assert_eq!(complementary.len(), 1);
+
+        let difference = (hue(&complementary[0].color) - hue(&palette[0].color)).rem_euclid(360.0);
+        assert!((difference - 180.0).abs() < 10.0, "{difference}");
+    }
+
+    #[test]
+    fn test_weight_is_shared() {
+        let palette = vec![WeightedColor {
+            color: [60, 120, 200],
+            freq: 0.6,
+        }];
+        let triadic = harmony_palette(&palette, Harmony::Triadic);
+        assert_eq!(triadic.len(), 2);
+        assert!(triadic.iter().all(|wc| (wc.freq - 0.3).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_greys_stay_grey() {
+        let palette = vec![WeightedColor {
+            color: [128, 128, 128],
+            freq: 1.0,
+        }];
+        let color = harmony_palette(&palette, Harmony::Analogous)[0].color;
+        assert!(color.iter().all(|c| c.abs_diff(128) <= 1));
+    }
+}
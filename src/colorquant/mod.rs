@@ -1,47 +1,124 @@
+pub mod accent;
+pub mod color;
+pub mod contrast;
+pub mod cvd;
+pub mod decode;
 pub mod distance;
+pub mod focus;
+pub mod harmony;
 pub mod quantize;
+pub mod saliency;
 
-pub use distance::palette_distance;
-pub use quantize::{get_palette, WeightedColor};
+pub use accent::AccentCurve;
+pub use color::{ColorSpace, Lab, LinearRgb, Oklab, Space, Srgb};
+pub use contrast::{contrast_penalty, Background};
+pub use cvd::{Cvd, Deficiency};
+pub use decode::{
+    decode_frames, decode_image, open_frames, open_image, read_frames, read_image, Frames,
+    WeightedFrame,
+};
+pub use distance::{
+    explain_distance, palette_distance, palette_distance_in, palette_distance_with, similarity,
+    ColorPair, MatchMode,
+};
+pub use focus::{Crop, Focus, Region, RegionPreset};
+pub use harmony::{harmony_palette, Harmony};
+pub use quantize::{get_palette, WeightedColor, LEVELS_RANGE, PALETTE_SIZE_RANGE};
 
 use crate::sprite::Sprite;
 use crate::{DEFAULT_LEVELS, DEFAULT_PALETTE_SIZE};
-use anyhow::Result;
-use image;
+use anyhow::{anyhow, Result};
+use image::{self, DynamicImage};
 
 pub fn get_image_palette(path: &str) -> Result<Vec<WeightedColor>> {
+    let image = open_image(path)?;
+
+    get_rgba_palette(&image, DEFAULT_PALETTE_SIZE, DEFAULT_LEVELS, false)
+}
+
+pub fn get_rgba_palette(
+    image: &DynamicImage,
+    palette_size: usize,
+    levels: usize,
+    ignore_black: bool,
+) -> Result<Vec<WeightedColor>> {
     // Convert to rgba first to filter transparent pixels
-    let rgba = image::open(path)?.to_rgba8();
-
-    let colors: Vec<[u8; 3]> = rgba
-        .pixels()
-        .filter_map(|pixel| {
-            if pixel[3] == 0 {
-                // Skip fully transparent pixels
-                None
-            } else {
-                Some([pixel[0], pixel[1], pixel[2]])
-            }
-        })
+    get_frames_palette(
+        &[WeightedFrame::from(image)],
+        &Focus::default(),
+        palette_size,
+        levels,
+        ignore_black,
+    )
+}
+
+/// Palette of one histogram across all frames, each weighted by its frame
+/// and by the focus on the image. Fails when the focus leaves no pixel.
+pub fn get_frames_palette(
+    frames: &[WeightedFrame],
+    focus: &Focus,
+    palette_size: usize,
+    levels: usize,
+    ignore_black: bool,
+) -> Result<Vec<WeightedColor>> {
+    let weights: Vec<Vec<f32>> = frames
+        .iter()
+        .map(|frame| focus.weights(&frame.image))
         .collect();
+    if weights.iter().flatten().all(|weight| *weight <= 0.0) {
+        return Err(anyhow!("No visible pixels in the selected region"));
+    }
+
+    let pixels = frames.iter().zip(weights).flat_map(|(frame, weights)| {
+        frame
+            .image
+            .pixels()
+            .zip(weights)
+            .filter(|(_, weight)| *weight > 0.0)
+            .map(|(pixel, weight)| ([pixel[0], pixel[1], pixel[2]], weight * frame.weight))
+    });
 
-    Ok(quantize::get_palette(
-        &colors,
-        DEFAULT_PALETTE_SIZE,
-        DEFAULT_LEVELS,
-        false,
+    Ok(quantize::get_weighted_palette(
+        pixels,
+        palette_size,
+        levels,
+        ignore_black,
     ))
 }
 
 pub fn get_pokemon_ranked<'a>(
-    image_palette: &Vec<WeightedColor>,
-    sprites: &'a Vec<Sprite>,
+    image_palette: &[WeightedColor],
+    sprites: impl IntoIterator<Item = &'a Sprite>,
 ) -> Vec<(&'a Sprite, f32)> {
-    let mut distances: Vec<(&'a Sprite, f32)> = sprites
-        .iter()
-        .map(|sprite| {
-            let dist = distance::palette_distance(&sprite.palette, &image_palette);
-            (sprite, dist)
+    get_pokemon_ranked_by(image_palette, sprites, palette_distance)
+}
+
+pub fn get_pokemon_ranked_by<'a>(
+    image_palette: &[WeightedColor],
+    sprites: impl IntoIterator<Item = &'a Sprite>,
+    metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32,
+) -> Vec<(&'a Sprite, f32)> {
+    rank_palettes(
+        image_palette,
+        sprites
+            .into_iter()
+            .map(|sprite| (sprite, sprite.palette.as_slice())),
+        metric,
+    )
+}
+
+/// Rank arbitrary items by the distance of their palette to `target`,
+/// closest first
+pub fn rank_palettes<'a, T>(
+    target: &[WeightedColor],
+    candidates: impl IntoIterator<Item = (T, &'a [WeightedColor])>,
+    metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32,
+) -> Vec<(T, f32)> {
+    let mut distances: Vec<(T, f32)> = candidates
+        .into_iter()
+        .map(|(item, palette)| {
+            let dist = metric(palette, target);
+            (item, dist)
         })
         .collect();
 
@@ -49,3 +126,35 @@ pub fn get_pokemon_ranked<'a>(
 
     distances
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(color: [u8; 3]) -> Vec<WeightedColor> {
+        vec![WeightedColor { color, freq: 1.0 }]
+    }
+
+    #[test]
+    fn test_rank_palettes_closest_first() {
+        let target = palette([250, 120, 40]);
+        let (blue, red, orange) = (
+            palette([40, 80, 220]),
+            palette([220, 30, 30]),
+            palette([240, 128, 48]),
+        );
+
+        let ranked = rank_palettes(
+            &target,
+            [
+                ("blue", blue.as_slice()),
+                ("red", red.as_slice()),
+                ("orange", orange.as_slice()),
+            ],
+            palette_distance,
+        );
+        let names: Vec<_> = ranked.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["orange", "red", "blue"]);
+        assert!(ranked.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+}
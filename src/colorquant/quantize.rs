@@ -1,24 +1,65 @@
+use super::color::{ColorSpace, LinearRgb};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 
+/// Palette color with its share of the image. Palettes are quantized and
+/// stored as 8-bit sRGB, `to_space` converts them for comparison.
 #[derive(Serialize, Deserialize)]
-pub struct WeightedColor {
-    pub color: [u8; 3],
+pub struct WeightedColor<C = [u8; 3]> {
+    pub color: C,
     pub freq: f32,
 }
 
+impl WeightedColor {
+    pub fn to_space<C: ColorSpace>(&self) -> WeightedColor<C> {
+        WeightedColor {
+            color: C::from_rgb(self.color),
+            freq: self.freq,
+        }
+    }
+}
+
+impl<C: ColorSpace> WeightedColor<C> {
+    pub fn to_rgb(&self) -> WeightedColor {
+        WeightedColor {
+            color: self.color.to_rgb(),
+            freq: self.freq,
+        }
+    }
+}
+
+/// Palette sizes and quantization levels per channel `get_palette` accepts
+pub const PALETTE_SIZE_RANGE: RangeInclusive<usize> = 1..=10;
+pub const LEVELS_RANGE: RangeInclusive<usize> = 2..=16;
+
 pub fn get_palette(
-    pixels: &Vec<[u8; 3]>,
+    pixels: &[[u8; 3]],
+    palette_size: usize,
+    levels: usize,
+    ignore_black: bool,
+) -> Vec<WeightedColor> {
+    get_weighted_palette(
+        pixels.iter().map(|&pixel| (pixel, 1.0)),
+        palette_size,
+        levels,
+        ignore_black,
+    )
+}
+
+/// Same as `get_palette`, but each pixel counts with its own weight
+pub fn get_weighted_palette(
+    pixels: impl IntoIterator<Item = ([u8; 3], f32)>,
     palette_size: usize,
     levels: usize,
     ignore_black: bool,
 ) -> Vec<WeightedColor> {
     // Sensible ranges
-    if levels < 2 || levels > 16 {
+    if !LEVELS_RANGE.contains(&levels) {
         panic!("levels must be between 2 and 16");
     }
 
-    if palette_size < 1 || palette_size > 10 {
+    if !PALETTE_SIZE_RANGE.contains(&palette_size) {
         panic!("palette_size must be between 1 and 10");
     }
 
@@ -31,35 +72,69 @@ pub fn get_palette(
         );
     }
 
-    // Check for empty input
-    if pixels.is_empty() {
-        panic!("cannot generate palette from empty pixel array");
-    }
-
     let bucket_size = (256 / levels) as u8;
 
-    let mut quantized_counts = HashMap::new();
-    for pixel in pixels {
-        if ignore_black && *pixel == [0, 0, 0] {
+    // Accumulate in f64, f32 sums of many pixels lose precision
+    let mut pixel_count = 0;
+    let mut total_weight = 0.0;
+    let mut buckets: HashMap<[u8; 3], Bucket> = HashMap::new();
+    for (pixel, weight) in pixels {
+        pixel_count += 1;
+        total_weight += weight as f64;
+        if ignore_black && pixel == [0, 0, 0] {
             continue;
         }
-        let qcolor = quantize_color(pixel, bucket_size);
-        *quantized_counts.entry(qcolor).or_insert(0) += 1;
+        let qcolor = quantize_color(&pixel, bucket_size);
+        buckets.entry(qcolor).or_default().add(pixel, weight as f64);
+    }
+
+    // Check for empty input
+    if pixel_count == 0 {
+        panic!("cannot generate palette from empty pixel array");
     }
 
-    let mut sorted: Vec<_> = quantized_counts.into_iter().collect();
-    sorted.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut sorted: Vec<_> = buckets.into_values().collect();
+    sorted.sort_by(|a, b| {
+        b.weight
+            .partial_cmp(&a.weight)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
 
     sorted
         .into_iter()
         .take(palette_size)
-        .map(|(color, count)| WeightedColor {
-            color: color,
-            freq: count as f32 / pixels.len() as f32,
+        .map(|bucket| WeightedColor {
+            color: bucket.mean_color(),
+            freq: (bucket.weight / total_weight) as f32,
         })
         .collect()
 }
 
+/// Pixels falling into one quantized color, summed in linear light so their
+/// mean is gamma-correct
+#[derive(Default)]
+struct Bucket {
+    weight: f64,
+    linear_sum: [f64; 3],
+}
+
+impl Bucket {
+    fn add(&mut self, pixel: [u8; 3], weight: f64) {
+        let linear = LinearRgb::from_rgb(pixel).components();
+        for (sum, channel) in self.linear_sum.iter_mut().zip(linear) {
+            *sum += channel as f64 * weight;
+        }
+        self.weight += weight;
+    }
+
+    fn mean_color(&self) -> [u8; 3] {
+        if self.weight <= 0.0 {
+            return [0, 0, 0];
+        }
+        LinearRgb::from_components(self.linear_sum.map(|sum| (sum / self.weight) as f32)).to_rgb()
+    }
+}
+
 fn quantize_color(color: &[u8; 3], bucket_size: u8) -> [u8; 3] {
     let channel0 = (color[0] / bucket_size) * bucket_size + (bucket_size / 2);
     let channel1 = (color[1] / bucket_size) * bucket_size + (bucket_size / 2);
@@ -153,6 +228,14 @@ mod tests {
         assert!(palette[0].freq > palette[1].freq);
     }
 
+    #[test]
+    fn test_get_weighted_palette_weights_matter() {
+        let pixels = vec![([255, 0, 0], 3.0), ([0, 255, 0], 1.0)];
+        let palette = get_weighted_palette(pixels, 2, 4, false);
+        assert_eq!(palette[0].freq, 0.75);
+        assert_eq!(palette[1].freq, 0.25);
+    }
+
     #[test]
     #[should_panic(expected = "levels must be between 2 and 16")]
     fn test_get_palette_invalid_levels_low() {
@@ -173,4 +256,23 @@ mod tests {
         let pixels: Vec<[u8; 3]> = vec![];
         get_palette(&pixels, 1, 4, false);
     }
+
+    #[test]
+    fn test_get_palette_reports_mean_color() {
+        // A lone color is reported as is, not as the center of its bucket
+        let pixels = vec![[0, 64, 128]; 10];
+        let palette = get_palette(&pixels, 1, 4, false);
+        assert_eq!(palette[0].color, [0, 64, 128]);
+    }
+
+    #[test]
+    fn test_get_palette_mean_is_gamma_correct() {
+        // Half black and half bucket-mate grey average in linear light, which
+        // is brighter than the sRGB midpoint
+        let mut pixels = vec![[0, 0, 0]; 50];
+        pixels.extend(vec![[60, 60, 60]; 50]);
+        let palette = get_palette(&pixels, 1, 4, false);
+        assert!(palette[0].color[0] > 30, "{:?}", palette[0].color);
+        assert!(palette[0].color[0] < 60);
+    }
 }
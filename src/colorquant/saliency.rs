@@ -0,0 +1,163 @@
+use image::RgbaImage;
+
+/// Largest per-channel difference between neighbouring pixels of one
+/// background region
+const BACKGROUND_TOLERANCE: u8 = 12;
+
+/// Border-connected regions covering at least this share of the image are
+/// treated as background
+const BACKGROUND_MIN_SHARE: f32 = 0.05;
+
+/// Weight left to background pixels
+const BACKGROUND_WEIGHT: f32 = 0.1;
+
+/// Weight of pixels in the corners relative to the centre
+const EDGE_WEIGHT: f32 = 0.5;
+
+/// How much local contrast can multiply the weight of a pixel
+const CONTRAST_BOOST: f32 = 2.0;
+
+/// Weight of each pixel by how likely it is to be part of the subject, in
+/// row-major order. Large uniform regions touching the border are
+/// down-weighted as background, central and high-contrast pixels are boosted.
+pub fn saliency_weights(image: &RgbaImage) -> Vec<f32> {
+    let (width, height) = image.dimensions();
+    let background = background_mask(image);
+    let luma: Vec<f32> = image
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect();
+
+    let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+    let max_dist = (cx * cx + cy * cy).sqrt().max(1.0);
+
+    let mut weights = Vec::with_capacity(luma.len());
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+
+            let dist = ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt() / max_dist;
+            let center = 1.0 - (1.0 - EDGE_WEIGHT) * dist;
+
+            let contrast = neighbours(x, y, width, height)
+                .map(|j| (luma[i] - luma[j]).abs())
+                .fold(0.0, f32::max)
+                / 255.0;
+
+            let mut weight = center * (1.0 + CONTRAST_BOOST * contrast);
+            if background[i] {
+                weight *= BACKGROUND_WEIGHT;
+            }
+            weights.push(weight);
+        }
+    }
+
+    weights
+}
+
+/// Flood-fill from the border, marking pixels of large uniform regions
+fn background_mask(image: &RgbaImage) -> Vec<bool> {
+    let (width, height) = image.dimensions();
+    let pixel_count = (width * height) as usize;
+    let min_size = (pixel_count as f32 * BACKGROUND_MIN_SHARE).ceil() as usize;
+
+    let mut visited = vec![false; pixel_count];
+    let mut background = vec![false; pixel_count];
+    if pixel_count == 0 {
+        return background;
+    }
+    let similar = |a: usize, b: usize| {
+        let (a, b) = (pixel_at(image, a), pixel_at(image, b));
+        a[3] > 0 && b[3] > 0 && (0..3).all(|c| a[c].abs_diff(b[c]) <= BACKGROUND_TOLERANCE)
+    };
+
+    let border = (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+
+    for (x, y) in border {
+        let seed = (y * width + x) as usize;
+        if visited[seed] || pixel_at(image, seed)[3] == 0 {
+            continue;
+        }
+
+        visited[seed] = true;
+        let mut region = vec![seed];
+        let mut stack = vec![seed];
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            for j in neighbours(x, y, width, height) {
+                if !visited[j] && similar(i, j) {
+                    visited[j] = true;
+                    region.push(j);
+                    stack.push(j);
+                }
+            }
+        }
+
+        if region.len() >= min_size {
+            for i in region {
+                background[i] = true;
+            }
+        }
+    }
+
+    background
+}
+
+fn pixel_at(image: &RgbaImage, i: usize) -> [u8; 4] {
+    let width = image.width() as usize;
+    image.get_pixel((i % width) as u32, (i / width) as u32).0
+}
+
+/// Indices of the 4-connected neighbours of a pixel
+fn neighbours(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = usize> {
+    [
+        (x > 0).then(|| (x - 1, y)),
+        (x + 1 < width).then(|| (x + 1, y)),
+        (y > 0).then(|| (x, y - 1)),
+        (y + 1 < height).then(|| (x, y + 1)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(move |(x, y)| (y * width + x) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    // Grey studio background around a red subject
+    fn portrait() -> RgbaImage {
+        RgbaImage::from_fn(20, 20, |x, y| {
+            if (7..13).contains(&x) && (7..13).contains(&y) {
+                Rgba([220, 30, 30, 255])
+            } else {
+                Rgba([128, 128, 128, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_background_is_detected() {
+        let background = background_mask(&portrait());
+        assert!(background[0]);
+        assert!(!background[10 * 20 + 10]);
+    }
+
+    #[test]
+    fn test_subject_outweighs_background() {
+        let weights = saliency_weights(&portrait());
+        let subject: f32 = weights[10 * 20 + 10];
+        let background: f32 = weights[3 * 20 + 10];
+        assert!(subject > background * 5.0);
+    }
+
+    #[test]
+    fn test_small_border_regions_are_kept() {
+        // Every pixel differs from its neighbours, no region is large enough
+        let image = RgbaImage::from_fn(10, 10, |x, y| Rgba([((x + y) % 2 * 200) as u8, 0, 0, 255]));
+        assert!(background_mask(&image).iter().all(|b| !b));
+    }
+}
@@ -1,10 +1,13 @@
 pub mod cli;
 pub mod colorquant;
+pub mod matcher;
 pub mod pokemondatabase;
+pub mod ranking;
 pub mod sprite;
 
 pub use cli::output::{print_image_information, print_result, print_top_information};
 pub use colorquant::{get_palette, palette_distance, WeightedColor};
+pub use matcher::{Match, PaletteMatcher, PaletteMatcherBuilder};
 
 pub const KRABBY_BASE_URL: &str = "https://raw.githubusercontent.com/yannjor/krabby/main/";
 pub const DB_FILE_NAME: &str = "pokemon.bin";
@@ -1,34 +1,17 @@
 use anyhow::Result;
-use clap::Parser;
-use pokepalette::cli::args::Args;
-use pokepalette::colorquant::{get_image_palette, get_pokemon_ranked};
-use pokepalette::pokemondatabase::PokemonDatabase;
-use pokepalette::sprite::Sprite;
-use pokepalette::{print_image_information, print_result, print_top_information};
+use pokepalette::cli::args::Command;
+use pokepalette::cli::commands::{cache, config, match_images, similar, wallpaper_for, watch};
+use pokepalette::cli::config::parse_args;
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = parse_args()?;
 
-    // Load database and filter
-    let database = PokemonDatabase::load()?;
-    let sprites = database.filtered(&args)?;
-
-    // Generate image palette
-    let image_palette = get_image_palette(&args.image)?;
-
-    // Get sprites sorted by distance to image
-    let ranked = get_pokemon_ranked(&image_palette, &sprites);
-
-    // Get top k
-    let top: Vec<(&Sprite, f32)> = ranked.into_iter().take(args.top_k).collect();
-
-    // Print results
-    if args.verbose {
-        print_image_information(image_palette);
-        print_top_information(&top);
-    } else {
-        print_result(&top);
+    match &args.command {
+        Some(Command::WallpaperFor(wallpaper_args)) => wallpaper_for::run(wallpaper_args),
+        Some(Command::Similar(similar_args)) => similar::run(similar_args),
+        Some(Command::Watch(watch_args)) => watch::run(watch_args),
+        Some(Command::Config(config_args)) => config::run(&args, &config_args.command),
+        Some(Command::Cache(cache_args)) => cache::run(&cache_args.command),
+        None => match_images::run(&args),
     }
-
-    Ok(())
 }
@@ -0,0 +1,468 @@
+use crate::colorquant::{
+    contrast_penalty, get_frames_palette, get_pokemon_ranked_by, harmony_palette, open_image,
+    palette_distance, rank_palettes, similarity, Background, Focus, Harmony, WeightedColor,
+    WeightedFrame, LEVELS_RANGE, PALETTE_SIZE_RANGE,
+};
+use crate::pokemondatabase::{PokemonDatabase, SpriteFilter};
+use crate::ranking::{distinct, diversify, Distinct};
+use crate::sprite::Sprite;
+use crate::{DEFAULT_LEVELS, DEFAULT_PALETTE_SIZE, DEFAULT_TOP_K};
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+/// Distance between two palettes, lower is more similar
+pub type Metric = Box<dyn Fn(&[WeightedColor], &[WeightedColor]) -> f32 + Send + Sync>;
+
+/// A sprite matched against an image palette
+#[derive(Clone, Copy)]
+pub struct Match<'a> {
+    pub sprite: &'a Sprite,
+    pub distance: f32,
+}
+
+/// Ranks the sprites of a database against image palettes.
+///
+/// ```no_run
+/// use pokepalette::PaletteMatcher;
+///
+/// let matcher = PaletteMatcher::builder().top_k(5).build()?;
+/// for m in matcher.match_path("wallpaper.png")? {
+///     println!("{} {}", m.sprite.name, m.distance);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct PaletteMatcher {
+    database: PokemonDatabase,
+    filter: SpriteFilter,
+    palette_size: usize,
+    levels: usize,
+    ignore_black: bool,
+    focus: Focus,
+    metric: Metric,
+    harmony: Option<Harmony>,
+    background: Option<Background>,
+    top_k: usize,
+    distinct: Distinct,
+    max_per_species: Option<NonZeroUsize>,
+    diversity: f32,
+}
+
+pub struct PaletteMatcherBuilder {
+    database: Option<PokemonDatabase>,
+    filter: SpriteFilter,
+    palette_size: usize,
+    levels: usize,
+    ignore_black: bool,
+    focus: Focus,
+    metric: Metric,
+    harmony: Option<Harmony>,
+    background: Option<Background>,
+    top_k: usize,
+    distinct: Distinct,
+    max_per_species: Option<NonZeroUsize>,
+    diversity: f32,
+}
+
+impl Match<'_> {
+    /// Similarity from 0% to 100%
+    pub fn similarity(&self) -> f32 {
+        similarity(self.distance)
+    }
+}
+
+impl PaletteMatcher {
+    pub fn builder() -> PaletteMatcherBuilder {
+        PaletteMatcherBuilder::default()
+    }
+
+    pub fn database(&self) -> &PokemonDatabase {
+        &self.database
+    }
+
+    pub fn focus(&self) -> &Focus {
+        &self.focus
+    }
+
+    /// Sprites considered for matching, after filtering
+    pub fn sprites(&self) -> impl Iterator<Item = &Sprite> {
+        self.database.filter(&self.filter)
+    }
+
+    pub fn image_palette(&self, image: &DynamicImage) -> Result<Vec<WeightedColor>> {
+        self.frames_palette(&[WeightedFrame::from(image)])
+    }
+
+    /// Palette across the frames of an animated image, failing when nothing
+    /// of the frames is left in focus
+    pub fn frames_palette(&self, frames: &[WeightedFrame]) -> Result<Vec<WeightedColor>> {
+        get_frames_palette(
+            frames,
+            &self.focus,
+            self.palette_size,
+            self.levels,
+            self.ignore_black,
+        )
+    }
+
+    /// Every setting that changes the palette `frames_palette` returns, for
+    /// caching palettes
+    pub fn palette_key(&self) -> String {
+        let mask = self.focus.mask.as_ref().map(|mask| {
+            let mut hasher = DefaultHasher::new();
+            mask.dimensions().hash(&mut hasher);
+            mask.as_raw().hash(&mut hasher);
+            hasher.finish()
+        });
+
+        format!(
+            "palette_size={} levels={} ignore_black={} region={:?} mask={:?} saliency={}",
+            self.palette_size,
+            self.levels,
+            self.ignore_black,
+            self.focus.region,
+            mask,
+            self.focus.saliency,
+        )
+    }
+
+    pub fn path_palette(&self, path: impl AsRef<Path>) -> Result<Vec<WeightedColor>> {
+        let image = open_image(path)?;
+        self.image_palette(&image)
+    }
+
+    pub fn match_image(&self, image: &DynamicImage) -> Result<Vec<Match<'_>>> {
+        Ok(self.match_palette(&self.image_palette(image)?))
+    }
+
+    pub fn match_path(&self, path: impl AsRef<Path>) -> Result<Vec<Match<'_>>> {
+        Ok(self.match_palette(&self.path_palette(path)?))
+    }
+
+    pub fn match_palette(&self, palette: &[WeightedColor]) -> Vec<Match<'_>> {
+        let background = self
+            .background
+            .and_then(|background| background.resolve(palette));
+
+        let harmonized;
+        let palette = match self.harmony {
+            Some(harmony) => {
+                harmonized = harmony_palette(palette, harmony);
+                &harmonized
+            }
+            None => palette,
+        };
+
+        let ranked = match background {
+            // Sprites that would disappear on the background rank lower
+            Some(background) => get_pokemon_ranked_by(palette, self.sprites(), |sprite, image| {
+                (self.metric)(sprite, image) + contrast_penalty(sprite, &background)
+            }),
+            None => get_pokemon_ranked_by(palette, self.sprites(), &self.metric),
+        };
+        let ranked = distinct(ranked, self.distinct, self.max_per_species);
+        let ranked = diversify(ranked, self.top_k, self.diversity);
+
+        ranked
+            .into_iter()
+            .take(self.top_k)
+            .map(|(sprite, distance)| Match { sprite, distance })
+            .collect()
+    }
+}
+
+impl PaletteMatcher {
+    /// Rank arbitrary palettes against `target` with the configured metric,
+    /// e.g. images against a sprite palette
+    pub fn rank<'a, T>(
+        &self,
+        target: &[WeightedColor],
+        candidates: impl IntoIterator<Item = (T, &'a [WeightedColor])>,
+    ) -> Vec<(T, f32)> {
+        rank_palettes(target, candidates, &self.metric)
+    }
+}
+
+impl Default for PaletteMatcherBuilder {
+    fn default() -> Self {
+        Self {
+            database: None,
+            filter: SpriteFilter::new(),
+            palette_size: DEFAULT_PALETTE_SIZE,
+            levels: DEFAULT_LEVELS,
+            ignore_black: false,
+            focus: Focus::default(),
+            metric: Box::new(palette_distance),
+            harmony: None,
+            background: None,
+            top_k: DEFAULT_TOP_K,
+            distinct: Distinct::None,
+            max_per_species: None,
+            diversity: 0.0,
+        }
+    }
+}
+
+impl PaletteMatcherBuilder {
+    /// Database to match against, loaded from `DB_FILE_NAME` if not set
+    pub fn database(mut self, database: PokemonDatabase) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Only match sprites kept by the filter
+    pub fn filter(mut self, filter: SpriteFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Number of colors extracted from images
+    pub fn palette_size(mut self, palette_size: usize) -> Self {
+        self.palette_size = palette_size;
+        self
+    }
+
+    /// Quantization levels per channel
+    pub fn levels(mut self, levels: usize) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    pub fn ignore_black(mut self, ignore_black: bool) -> Self {
+        self.ignore_black = ignore_black;
+        self
+    }
+
+    /// Parts of images that feed their palette
+    pub fn focus(mut self, focus: Focus) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Palette distance used for ranking, `palette_distance` by default
+    pub fn metric(
+        mut self,
+        metric: impl Fn(&[WeightedColor], &[WeightedColor]) -> f32 + Send + Sync + 'static,
+    ) -> Self {
+        self.metric = Box::new(metric);
+        self
+    }
+
+    /// Match sprites in harmony with palettes instead of similar ones
+    pub fn harmony(mut self, harmony: Option<Harmony>) -> Self {
+        self.harmony = harmony;
+        self
+    }
+
+    /// Penalize sprites lacking contrast with the background they are shown on
+    pub fn background(mut self, background: Option<Background>) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn distinct(mut self, distinct: Distinct) -> Self {
+        self.distinct = distinct;
+        self
+    }
+
+    pub fn max_per_species(mut self, max_per_species: Option<NonZeroUsize>) -> Self {
+        self.max_per_species = max_per_species;
+        self
+    }
+
+    pub fn diversity(mut self, diversity: f32) -> Self {
+        self.diversity = diversity;
+        self
+    }
+
+    pub fn build(self) -> Result<PaletteMatcher> {
+        if !PALETTE_SIZE_RANGE.contains(&self.palette_size) {
+            return Err(anyhow!(
+                "Palette size {} is out of range (must be {}-{})",
+                self.palette_size,
+                PALETTE_SIZE_RANGE.start(),
+                PALETTE_SIZE_RANGE.end()
+            ));
+        }
+        if !LEVELS_RANGE.contains(&self.levels) {
+            return Err(anyhow!(
+                "Quantization levels {} are out of range (must be {}-{})",
+                self.levels,
+                LEVELS_RANGE.start(),
+                LEVELS_RANGE.end()
+            ));
+        }
+
+        let database = match self.database {
+            Some(database) => database,
+            None => PokemonDatabase::load()?,
+        };
+
+        Ok(PaletteMatcher {
+            database,
+            filter: self.filter,
+            palette_size: self.palette_size,
+            levels: self.levels,
+            ignore_black: self.ignore_black,
+            focus: self.focus,
+            metric: self.metric,
+            harmony: self.harmony,
+            background: self.background,
+            top_k: self.top_k,
+            distinct: self.distinct,
+            max_per_species: self.max_per_species,
+            diversity: self.diversity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colorquant::Region;
+
+    fn database() -> PokemonDatabase {
+        PokemonDatabase::from(vec![
+            Sprite::named("charmander").color([240, 128, 48]),
+            Sprite::named("squirtle").color([96, 160, 224]),
+            Sprite::named("bulbasaur").color([96, 192, 128]),
+        ])
+    }
+
+    fn names(matches: &[Match]) -> Vec<String> {
+        matches.iter().map(|m| m.sprite.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_build_validates_quantizer_settings() {
+        let build = |palette_size, levels| {
+            PaletteMatcher::builder()
+                .database(database())
+                .palette_size(palette_size)
+                .levels(levels)
+                .build()
+        };
+
+        assert!(build(1, 8).is_ok());
+        assert!(build(10, 8).is_ok());
+        assert!(build(0, 8).is_err());
+        assert!(build(11, 8).is_err());
+
+        assert!(build(5, 2).is_ok());
+        assert!(build(5, 16).is_ok());
+        assert!(build(5, 1).is_err());
+        assert!(build(5, 17).is_err());
+    }
+
+    #[test]
+    fn test_match_palette_closest_first() {
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .build()
+            .unwrap();
+        let palette = vec![WeightedColor {
+            color: [100, 160, 220],
+            freq: 1.0,
+        }];
+        let matches = matcher.match_palette(&palette);
+        assert_eq!(matches[0].sprite.name, "squirtle");
+        assert_eq!(matches.len(), 3);
+    }
+
+    #[test]
+    fn test_match_palette_respects_top_k_and_filter() {
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .filter(SpriteFilter::new().name(|name| name != "squirtle"))
+            .top_k(1)
+            .build()
+            .unwrap();
+        let palette = vec![WeightedColor {
+            color: [100, 160, 220],
+            freq: 1.0,
+        }];
+        assert_eq!(names(&matcher.match_palette(&palette)), vec!["bulbasaur"]);
+    }
+
+    #[test]
+    fn test_match_image() {
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .build()
+            .unwrap();
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            4,
+            image::Rgb([240, 130, 50]),
+        ));
+        assert_eq!(
+            matcher.match_image(&image).unwrap()[0].sprite.name,
+            "charmander"
+        );
+    }
+
+    #[test]
+    fn test_match_image_without_pixels_in_focus() {
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .focus(Focus {
+                region: Some(Region::Crop("8,8,4,4".parse().unwrap())),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            4,
+            image::Rgb([240, 130, 50]),
+        ));
+        assert!(matcher.match_image(&image).is_err());
+
+        let transparent = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .build()
+            .unwrap();
+        assert!(matcher.image_palette(&transparent).is_err());
+    }
+
+    #[test]
+    fn test_match_palette_harmony() {
+        let matcher = PaletteMatcher::builder()
+            .database(database())
+            .harmony(Some(Harmony::Complementary))
+            .build()
+            .unwrap();
+        let palette = vec![WeightedColor {
+            color: [240, 128, 48],
+            freq: 1.0,
+        }];
+        assert_eq!(matcher.match_palette(&palette)[0].sprite.name, "squirtle");
+    }
+
+    #[test]
+    fn test_match_palette_background_contrast() {
+        // Squirtle is closer, but blends into a white background
+        let database = PokemonDatabase::from(vec![
+            Sprite::named("squirtle").color([96, 160, 224]),
+            Sprite::named("greninja").color([40, 70, 140]),
+        ]);
+        let matcher = PaletteMatcher::builder()
+            .database(database)
+            .background(Some(Background::Color([255, 255, 255])))
+            .build()
+            .unwrap();
+        let palette = vec![WeightedColor {
+            color: [100, 160, 220],
+            freq: 1.0,
+        }];
+        assert_eq!(matcher.match_palette(&palette)[0].sprite.name, "greninja");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::sprite::{Form, Gender, Sprite};
+use anyhow::{anyhow, Result};
+use std::ops::Not;
+
+const MIN_GEN: u8 = 1;
+const MAX_GEN: u8 = 9;
+
+type Predicate = Box<dyn Fn(&Sprite) -> bool + Send + Sync>;
+
+/// Composable predicate over sprites.
+///
+/// Each method narrows the filter, `and`, `or` and `!` combine whole filters:
+///
+/// ```
+/// use pokepalette::pokemondatabase::SpriteFilter;
+///
+/// let filter = SpriteFilter::new()
+///     .gen([1, 2, 3])
+///     .shiny(false)
+///     .or(SpriteFilter::new().name(|name| name == "mew"));
+/// ```
+pub struct SpriteFilter {
+    predicate: Predicate,
+}
+
+impl Default for SpriteFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpriteFilter {
+    /// Filter that keeps every sprite
+    pub fn new() -> Self {
+        Self {
+            predicate: Box::new(|_| true),
+        }
+    }
+
+    /// Keep sprites for which both filters hold
+    pub fn and(self, other: SpriteFilter) -> Self {
+        Self {
+            predicate: Box::new(move |sprite| self.matches(sprite) && other.matches(sprite)),
+        }
+    }
+
+    /// Keep sprites for which either filter holds
+    pub fn or(self, other: SpriteFilter) -> Self {
+        Self {
+            predicate: Box::new(move |sprite| self.matches(sprite) || other.matches(sprite)),
+        }
+    }
+
+    /// Keep sprites for which an arbitrary predicate holds
+    pub fn when(self, predicate: impl Fn(&Sprite) -> bool + Send + Sync + 'static) -> Self {
+        self.and(Self {
+            predicate: Box::new(predicate),
+        })
+    }
+
+    pub fn gen(self, gens: impl IntoIterator<Item = u8>) -> Self {
+        let gens: Vec<u8> = gens.into_iter().collect();
+        self.when(move |sprite| gens.contains(&sprite.gen))
+    }
+
+    pub fn shiny(self, shiny: bool) -> Self {
+        self.when(move |sprite| sprite.shiny == shiny)
+    }
+
+    pub fn mega(self, mega: bool) -> Self {
+        self.when(move |sprite| matches!(sprite.form, Form::Mega(_)) == mega)
+    }
+
+    pub fn gmax(self, gmax: bool) -> Self {
+        self.when(move |sprite| matches!(sprite.form, Form::Gmax) == gmax)
+    }
+
+    pub fn regional(self, regional: bool) -> Self {
+        self.when(move |sprite| matches!(sprite.form, Form::Regional(_)) == regional)
+    }
+
+    pub fn female(self, female: bool) -> Self {
+        self.when(move |sprite| (sprite.gender == Gender::Female) == female)
+    }
+
+    pub fn form(self, form: Form) -> Self {
+        self.when(move |sprite| sprite.form == form)
+    }
+
+    /// Keep a variant named the way krabby's `-f` names it. `female` keeps
+    /// the female variants of base forms, every other name only non-female ones.
+    pub fn variant(self, name: &str) -> Self {
+        match name {
+            "female" => self.form(Form::Regular).female(true),
+            name => self.form(Form::from(name)).female(false),
+        }
+    }
+
+    /// Keep the sprite krabby shows for `name [-s] [-f form]`
+    pub fn krabby(self, name: &str, shiny: bool, form: Option<&str>) -> Self {
+        let name = name.to_string();
+        self.name(move |other| other == name)
+            .shiny(shiny)
+            .variant(form.unwrap_or("regular"))
+    }
+
+    pub fn name(self, predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.when(move |sprite| predicate(&sprite.name))
+    }
+
+    pub fn matches(&self, sprite: &Sprite) -> bool {
+        (self.predicate)(sprite)
+    }
+}
+
+impl Not for SpriteFilter {
+    type Output = Self;
+
+    /// Keep sprites this filter would drop
+    fn not(self) -> Self {
+        Self {
+            predicate: Box::new(move |sprite| !self.matches(sprite)),
+        }
+    }
+}
+
+pub fn parse_generation(gen_str: &str) -> Result<Vec<u8>> {
+    let mut gens: Vec<u8> = Vec::new();
+    if gen_str.contains('-') {
+        // Range: "1-3" -> [1, 2, 3]
+        let parts: Vec<&str> = gen_str.split('-').collect();
+        if parts.len() != 2 {
+            return Err(anyhow!("Invalid range format: too many parts"));
+        }
+        let start: u8 = parts[0]
+            .parse()
+            .map_err(|_| anyhow!("Invalid start number: {}", parts[0]))?;
+        let end: u8 = parts[1]
+            .parse()
+            .map_err(|_| anyhow!("Invalid end number: {}", parts[1]))?;
+        if start >= end {
+            return Err(anyhow!(
+                "Invalid range format: start cannot be greater than or equal to end"
+            ));
+        }
+        gens.extend(start..=end);
+    } else if gen_str.contains(',') {
+        // Multiple: "1,2,4" -> [1, 2, 4]
+        for part in gen_str.split(',') {
+            let gen: u8 = part
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid number: {part}"))?;
+            gens.push(gen);
+        }
+    } else {
+        // Single "3" -> [3]
+        let gen: u8 = gen_str
+            .parse()
+            .map_err(|_| anyhow!("Invalid number: {gen_str})"))?;
+        gens.push(gen);
+    }
+
+    // Validate all generations are in valid range
+    for &gen in &gens {
+        if !(MIN_GEN..=MAX_GEN).contains(&gen) {
+            return Err(anyhow!("Generation {} is out of range (must be 1-9)", gen));
+        }
+    }
+
+    Ok(gens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::MegaType;
+
+    #[test]
+    fn test_parse_generation_formats() {
+        assert_eq!(parse_generation("3").unwrap(), vec![3]);
+        assert_eq!(parse_generation("1-3").unwrap(), vec![1, 2, 3]);
+        assert_eq!(parse_generation("1, 4,8").unwrap(), vec![1, 4, 8]);
+    }
+
+    #[test]
+    fn test_parse_generation_invalid() {
+        assert!(parse_generation("3-1").is_err());
+        assert!(parse_generation("10").is_err());
+        assert!(parse_generation("one").is_err());
+    }
+
+    #[test]
+    fn test_filter_narrows() {
+        let filter = SpriteFilter::new().gen([1]).shiny(false).mega(false);
+        assert!(filter.matches(&Sprite::named("charizard")));
+        assert!(!filter.matches(&Sprite::named("charizard").shiny(true)));
+        assert!(!filter.matches(&Sprite::named("charizard").form(Form::Mega(MegaType::X))));
+        assert!(!filter.matches(&Sprite::named("typhlosion").gen(2)));
+    }
+
+    #[test]
+    fn test_filter_gender() {
+        let female = Sprite::named("pikachu").female();
+        let regular = Sprite::named("pikachu");
+
+        assert!(SpriteFilter::new().female(true).matches(&female));
+        assert!(!SpriteFilter::new().female(true).matches(&regular));
+        assert!(!SpriteFilter::new().female(false).matches(&female));
+        assert!(SpriteFilter::new().female(false).matches(&regular));
+
+        let variant = SpriteFilter::new().variant("female");
+        assert!(variant.matches(&female));
+        assert!(!variant.matches(&regular));
+        let variant = SpriteFilter::new().variant("regular");
+        assert!(variant.matches(&regular));
+        assert!(!variant.matches(&female));
+    }
+
+    #[test]
+    fn test_filter_combinators() {
+        let filter = SpriteFilter::new()
+            .gen([2])
+            .or(SpriteFilter::new().name(|name| name == "mew"));
+        assert!(filter.matches(&Sprite::named("mew")));
+        assert!(filter.matches(&Sprite::named("typhlosion").gen(2)));
+        assert!(!filter.matches(&Sprite::named("charizard")));
+        assert!((!filter).matches(&Sprite::named("charizard")));
+    }
+}
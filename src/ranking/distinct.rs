@@ -0,0 +1,122 @@
+use crate::sprite::Sprite;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum Distinct {
+    /// One result per species
+    Species,
+    /// One result per species for regular and shiny variants each
+    SpeciesShiny,
+    /// Keep every variant
+    #[default]
+    None,
+}
+
+pub fn distinct(
+    ranked: Vec<(&Sprite, f32)>,
+    distinct: Distinct,
+    max_per_species: Option<NonZeroUsize>,
+) -> Vec<(&Sprite, f32)> {
+    // Number of variants kept per group, unlimited unless asked for
+    let limit = match (distinct, max_per_species) {
+        (_, Some(max)) => max.get(),
+        (Distinct::None, None) => return ranked,
+        (_, None) => 1,
+    };
+
+    // Ranked input is sorted, so the first variants seen are the best scoring
+    let mut counts: HashMap<(&str, Option<bool>), usize> = HashMap::new();
+    ranked
+        .into_iter()
+        .filter(|(sprite, _)| {
+            let shiny = match distinct {
+                Distinct::SpeciesShiny => Some(sprite.shiny),
+                _ => None,
+            };
+            let count = counts.entry((sprite.name.as_str(), shiny)).or_insert(0);
+            *count += 1;
+            *count <= limit
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::{Form, MegaType};
+
+    fn names(result: &[(&Sprite, f32)]) -> Vec<String> {
+        result
+            .iter()
+            .map(|(sprite, _)| format!("{} {} {}", sprite.name, sprite.shiny, sprite.form))
+            .collect()
+    }
+
+    fn sprites() -> Vec<Sprite> {
+        vec![
+            Sprite::named("charizard"),
+            Sprite::named("charizard").shiny(true),
+            Sprite::named("charizard").form(Form::Mega(MegaType::X)),
+            Sprite::named("blastoise").shiny(true),
+            Sprite::named("charizard").form(Form::Gmax),
+            Sprite::named("blastoise"),
+        ]
+    }
+
+    fn ranked(sprites: &[Sprite]) -> Vec<(&Sprite, f32)> {
+        sprites
+            .iter()
+            .enumerate()
+            .map(|(i, sprite)| (sprite, i as f32))
+            .collect()
+    }
+
+    #[test]
+    fn test_distinct_none_keeps_everything() {
+        let sprites = sprites();
+        let result = distinct(ranked(&sprites), Distinct::None, None);
+        assert_eq!(result.len(), sprites.len());
+    }
+
+    #[test]
+    fn test_distinct_species_keeps_best_variant() {
+        let sprites = sprites();
+        let result = distinct(ranked(&sprites), Distinct::Species, None);
+        assert_eq!(
+            names(&result),
+            vec!["charizard false regular", "blastoise true regular"]
+        );
+    }
+
+    #[test]
+    fn test_distinct_species_shiny_keeps_one_of_each() {
+        let sprites = sprites();
+        let result = distinct(ranked(&sprites), Distinct::SpeciesShiny, None);
+        assert_eq!(
+            names(&result),
+            vec![
+                "charizard false regular",
+                "charizard true regular",
+                "blastoise true regular",
+                "blastoise false regular",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_per_species() {
+        let sprites = sprites();
+        let result = distinct(ranked(&sprites), Distinct::None, NonZeroUsize::new(2));
+        assert_eq!(
+            names(&result),
+            vec![
+                "charizard false regular",
+                "charizard true regular",
+                "blastoise true regular",
+                "blastoise false regular",
+            ]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::colorquant::palette_distance;
+use crate::sprite::Sprite;
+
+// Only the best candidates are considered for re-ranking, keeps the
+// pairwise distance computations bounded
+const CANDIDATE_POOL_FACTOR: usize = 10;
+
+/// Maximal marginal relevance re-ranking. Each pick trades off the distance to
+/// the image against the distance to the sprites already picked, `diversity`
+/// ranges from 0.0 (plain ranking) to 1.0 (only dissimilarity counts).
+pub fn diversify(ranked: Vec<(&Sprite, f32)>, top_k: usize, diversity: f32) -> Vec<(&Sprite, f32)> {
+    if diversity <= 0.0 {
+        return ranked;
+    }
+
+    let mut candidates: Vec<(&Sprite, f32)> = ranked
+        .into_iter()
+        .take(top_k.saturating_mul(CANDIDATE_POOL_FACTOR))
+        .collect();
+
+    // Distance from each candidate to its closest already picked sprite
+    let mut min_distances = vec![0.0; candidates.len()];
+    let mut picked = Vec::with_capacity(top_k);
+
+    while picked.len() < top_k && !candidates.is_empty() {
+        // Nothing is picked to differ from yet, so the best match leads. Ties
+        // go to the better ranked candidate.
+        let best = candidates
+            .iter()
+            .zip(&min_distances)
+            .map(|((_, distance), min_distance)| {
+                (1.0 - diversity) * -distance + diversity * min_distance
+            })
+            .enumerate()
+            .take(if picked.is_empty() { 1 } else { usize::MAX })
+            .reduce(|best, score| if score.1 > best.1 { score } else { best })
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+
+        let (sprite, distance) = candidates.remove(best);
+        min_distances.remove(best);
+
+        for ((candidate, _), min_distance) in candidates.iter().zip(min_distances.iter_mut()) {
+            let d = palette_distance(&candidate.palette, &sprite.palette);
+            *min_distance = if picked.is_empty() {
+                d
+            } else {
+                min_distance.min(d)
+            };
+        }
+
+        picked.push((sprite, distance));
+    }
+
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(result: &[(&Sprite, f32)]) -> Vec<String> {
+        result
+            .iter()
+            .map(|(sprite, _)| sprite.name.clone())
+            .collect()
+    }
+
+    fn ranked(sprites: &[Sprite]) -> Vec<(&Sprite, f32)> {
+        sprites
+            .iter()
+            .enumerate()
+            .map(|(i, sprite)| (sprite, i as f32))
+            .collect()
+    }
+
+    fn sprites() -> Vec<Sprite> {
+        vec![
+            Sprite::named("blue").color([0, 0, 255]),
+            Sprite::named("also-blue").color([0, 0, 250]),
+            Sprite::named("red").color([255, 0, 0]),
+        ]
+    }
+
+    #[test]
+    fn test_diversify_zero_keeps_order() {
+        let sprites = sprites();
+        let result = diversify(ranked(&sprites), 2, 0.0);
+        assert_eq!(names(&result), vec!["blue", "also-blue", "red"]);
+    }
+
+    #[test]
+    fn test_diversify_skips_near_duplicates() {
+        let sprites = sprites();
+        let result = diversify(ranked(&sprites), 2, 0.5);
+        assert_eq!(names(&result), vec!["blue", "red"]);
+    }
+
+    #[test]
+    fn test_diversify_full_leads_with_best_match() {
+        let sprites = sprites();
+        let result = diversify(ranked(&sprites), 2, 1.0);
+        assert_eq!(names(&result), vec!["blue", "red"]);
+
+        // Equally diverse candidates keep their ranking
+        let sprites = vec![
+            Sprite::named("grey").color([128, 128, 128]),
+            Sprite::named("black").color([0, 0, 0]),
+            Sprite::named("also-black").color([0, 0, 0]),
+        ];
+        let result = diversify(ranked(&sprites), 2, 1.0);
+        assert_eq!(names(&result), vec!["grey", "black"]);
+    }
+
+    #[test]
+    fn test_diversify_keeps_image_distance() {
+        let sprites = sprites();
+        let result = diversify(ranked(&sprites), 3, 0.5);
+        assert_eq!(result[1].1, 2.0);
+        assert_eq!(result.len(), 3);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod distinct;
+pub mod diversity;
+pub mod pick;
+
+pub use distinct::{distinct, Distinct};
+pub use diversity::diversify;
+pub use pick::{pick_weighted, Pick};
@@ -0,0 +1,91 @@
+use clap::ValueEnum;
+use rand::Rng;
+
+/// Which of the top matches are returned
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum Pick {
+    /// All of them, best first
+    #[default]
+    All,
+    /// One at random, the more similar the more likely
+    Random,
+}
+
+/// Index of a random pick among the distances of ranked matches, chosen with
+/// a softmax over `-distance / temperature`: a match `temperature` further
+/// away than another is e times less likely. A temperature of 0.0 always
+/// picks the best match.
+pub fn pick_weighted(distances: &[f32], temperature: f32, rng: &mut impl Rng) -> Option<usize> {
+    let best = distances.iter().copied().fold(f32::INFINITY, f32::min);
+
+    if temperature <= 0.0 {
+        return distances.iter().position(|d| *d == best);
+    }
+
+    // Shifted by the best distance so the exponentials cannot overflow
+    let weights: Vec<f32> = distances
+        .iter()
+        .map(|d| ((best - d) / temperature).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+
+    let mut target = rng.random::<f32>() * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return Some(i);
+        }
+        target -= weight;
+    }
+
+    // Rounding can leave a sliver of the total past the last weight
+    weights.len().checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const DISTANCES: [f32; 3] = [10.0, 20.0, 60.0];
+
+    fn counts(temperature: f32, seed: u64) -> [usize; 3] {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[pick_weighted(&DISTANCES, temperature, &mut rng).unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_zero_temperature_picks_the_best() {
+        assert_eq!(counts(0.0, 1), [1000, 0, 0]);
+        assert_eq!(pick_weighted(&[], 5.0, &mut StdRng::seed_from_u64(1)), None);
+    }
+
+    #[test]
+    fn test_more_similar_matches_are_more_likely() {
+        let [a, b, c] = counts(20.0, 1);
+        assert!(a > b && b > c, "{a} {b} {c}");
+        assert!(c > 0);
+
+        // A high temperature evens the odds out
+        let [a, _, c] = counts(1000.0, 1);
+        assert!(a.abs_diff(c) < 100, "{a} {c}");
+    }
+
+    #[test]
+    fn test_distances_past_zero_similarity_still_count() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let first = (0..1000)
+            .filter(|_| pick_weighted(&[120.0, 150.0], 10.0, &mut rng) == Some(0))
+            .count();
+        assert!(first > 900, "{first}");
+    }
+
+    #[test]
+    fn test_seed_makes_picks_reproducible() {
+        assert_eq!(counts(10.0, 42), counts(10.0, 42));
+    }
+}
@@ -7,6 +7,7 @@ pub enum Form {
     Mega(MegaType),
     Gmax,
     Regional(Region),
+    Other(String), // any other krabby form, e.g. "origin" or "therian"
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -32,6 +33,25 @@ impl fmt::Display for Form {
             Form::Mega(mega_type) => write!(f, "{}", mega_type),
             Form::Gmax => write!(f, "gmax"),
             Form::Regional(region) => write!(f, "{}", region),
+            Form::Other(form) => write!(f, "{}", form),
+        }
+    }
+}
+
+impl From<&str> for Form {
+    fn from(form: &str) -> Self {
+        match form {
+            "regular" => Form::Regular,
+            "mega" => Form::Mega(MegaType::Mega),
+            "mega-x" => Form::Mega(MegaType::X),
+            "mega-y" => Form::Mega(MegaType::Y),
+            "primal" => Form::Mega(MegaType::Primal),
+            "gmax" => Form::Gmax,
+            "alola" => Form::Regional(Region::Alola),
+            "galar" => Form::Regional(Region::Galar),
+            "hisui" => Form::Regional(Region::Hisui),
+            "paldea" => Form::Regional(Region::Paldea),
+            other => Form::Other(other.to_string()),
         }
     }
 }
@@ -57,3 +77,47 @@ impl fmt::Display for Region {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_from_known_names() {
+        assert_eq!(Form::from("regular"), Form::Regular);
+        assert_eq!(Form::from("mega-x"), Form::Mega(MegaType::X));
+        assert_eq!(Form::from("gmax"), Form::Gmax);
+        assert_eq!(Form::from("hisui"), Form::Regional(Region::Hisui));
+    }
+
+    #[test]
+    fn test_form_from_other_name() {
+        assert_eq!(Form::from("origin"), Form::Other("origin".to_string()));
+    }
+
+    #[test]
+    fn test_form_from_krabby_forms() {
+        for name in [
+            "heat",
+            "wash",
+            "frost",
+            "fan",
+            "mow",
+            "rapid-strike",
+            "origin",
+            "therian",
+            "sky",
+            "unbound",
+        ] {
+            assert_eq!(Form::from(name), Form::Other(name.to_string()));
+            assert_eq!(Form::from(name).to_string(), name);
+        }
+    }
+
+    #[test]
+    fn test_form_display_round_trip() {
+        for name in ["mega", "mega-y", "primal", "gmax", "alola", "therian"] {
+            assert_eq!(Form::from(name).to_string(), name);
+        }
+    }
+}
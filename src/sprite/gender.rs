@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Gender {
+    Regular, // default sprite, male or genderless
+    Female,  // krabby's female-difference sprite
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Gender::Regular => write!(f, "regular"),
+            Gender::Female => write!(f, "female"),
+        }
+    }
+}
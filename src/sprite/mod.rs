@@ -1,8 +1,10 @@
 pub mod form;
+pub mod gender;
 pub mod parser;
 
 use crate::colorquant::WeightedColor;
 pub use form::{Form, MegaType, Region};
+pub use gender::Gender;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -12,6 +14,7 @@ pub struct Sprite {
     pub gen: u8,
     pub shiny: bool,
     pub form: Form,
+    pub gender: Gender,
     pub palette: Vec<WeightedColor>,
 }
 
@@ -26,6 +29,9 @@ impl fmt::Display for Sprite {
         if self.form != Form::Regular {
             writeln!(f, "  Form: {}", self.form)?;
         }
+        if self.gender == Gender::Female {
+            writeln!(f, "  Female variant")?;
+        }
 
         writeln!(f, "  Top Colors:")?;
         for (i, weighted_color) in self.palette.iter().enumerate() {
@@ -46,3 +52,46 @@ impl fmt::Display for Sprite {
         Ok(())
     }
 }
+
+/// Sprites for tests: `Sprite::named("charizard").shiny(true).form(Form::Gmax)`
+/// starts from a regular gen 1 sprite without a palette
+#[cfg(test)]
+impl Sprite {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            gen: 1,
+            shiny: false,
+            form: Form::Regular,
+            gender: Gender::Regular,
+            palette: vec![],
+        }
+    }
+
+    pub fn gen(self, gen: u8) -> Self {
+        Self { gen, ..self }
+    }
+
+    pub fn shiny(self, shiny: bool) -> Self {
+        Self { shiny, ..self }
+    }
+
+    pub fn form(self, form: Form) -> Self {
+        Self { form, ..self }
+    }
+
+    pub fn female(self) -> Self {
+        Self {
+            gender: Gender::Female,
+            ..self
+        }
+    }
+
+    /// Palette of a single color
+    pub fn color(self, color: [u8; 3]) -> Self {
+        Self {
+            palette: vec![WeightedColor { color, freq: 1.0 }],
+            ..self
+        }
+    }
+}
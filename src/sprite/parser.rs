@@ -1,14 +1,25 @@
-use super::form::{Form, MegaType, Region};
+use super::form::Form;
+use super::gender::Gender;
 use crate::colorquant::get_palette;
 use crate::{DEFAULT_IGNORE_BLACK, DEFAULT_LEVELS, DEFAULT_PALETTE_SIZE};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use regex::Regex;
 
 impl super::Sprite {
-    pub fn from_content(content: &str, name: &str, gen: u8, shiny: bool) -> Result<Self> {
-        let (clean_name, form) = Self::parse_name_and_form(&name);
+    pub fn from_content(
+        content: &str,
+        name: &str,
+        form: Option<&str>,
+        gen: u8,
+        shiny: bool,
+        gender: Gender,
+    ) -> Result<Self> {
+        let form = form.map_or(Form::Regular, Form::from);
 
-        let colors = Self::extract_colors(&content)?;
+        let colors = Self::extract_colors(content)?;
+        if colors.is_empty() {
+            return Err(anyhow!("No colors found in sprite {}", name));
+        }
         let palette = get_palette(
             &colors,
             DEFAULT_PALETTE_SIZE,
@@ -17,56 +28,15 @@ impl super::Sprite {
         );
 
         Ok(Self {
-            name: clean_name,
+            name: name.to_string(),
             gen,
             shiny,
             form,
+            gender,
             palette,
         })
     }
 
-    fn parse_name_and_form(name: &str) -> (String, Form) {
-        match name {
-            s if s.ends_with("-mega-x") => match s.strip_suffix("-mega-x") {
-                Some(clean) => (clean.to_string(), Form::Mega(MegaType::X)),
-                None => panic!("Failed to strip '-mega-x' from '{}'", s),
-            },
-            s if s.ends_with("-mega-y") => match s.strip_suffix("-mega-y") {
-                Some(clean) => (clean.to_string(), Form::Mega(MegaType::Y)),
-                None => panic!("Failed to strip '-mega-y' from '{}'", s),
-            },
-            s if s.ends_with("-mega") => match s.strip_suffix("-mega") {
-                Some(clean) => (clean.to_string(), Form::Mega(MegaType::Mega)),
-                None => panic!("Failed to strip '-mega' from '{}'", s),
-            },
-            s if s.ends_with("-primal") => match s.strip_suffix("-primal") {
-                Some(clean) => (clean.to_string(), Form::Mega(MegaType::Primal)),
-                None => panic!("Failed to strip '-primal' from '{}'", s),
-            },
-            s if s.ends_with("-gmax") => match s.strip_suffix("-gmax") {
-                Some(clean) => (clean.to_string(), Form::Gmax),
-                None => panic!("Failed to strip '-gmax' from '{}'", s),
-            },
-            s if s.ends_with("-alola") => match s.strip_suffix("-alola") {
-                Some(clean) => (clean.to_string(), Form::Regional(Region::Alola)),
-                None => panic!("Failed to strip '-alola' from '{}'", s),
-            },
-            s if s.ends_with("-galar") => match s.strip_suffix("-galar") {
-                Some(clean) => (clean.to_string(), Form::Regional(Region::Galar)),
-                None => panic!("Failed to strip '-galar' from '{}'", s),
-            },
-            s if s.ends_with("-hisui") => match s.strip_suffix("-hisui") {
-                Some(clean) => (clean.to_string(), Form::Regional(Region::Hisui)),
-                None => panic!("Failed to strip '-hisui' from '{}'", s),
-            },
-            s if s.ends_with("-paldea") => match s.strip_suffix("-paldea") {
-                Some(clean) => (clean.to_string(), Form::Regional(Region::Paldea)),
-                None => panic!("Failed to strip '-paldea' from '{}'", s),
-            },
-            _ => (name.to_string(), Form::Regular),
-        }
-    }
-
     fn extract_colors(content: &str) -> Result<Vec<[u8; 3]>> {
         let re = Regex::new(r"\[(?:38|48);2;(\d+);(\d+);(\d+)m")?;
 